@@ -0,0 +1,151 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, text, scrollable, row, column, Column };
+
+use crate::storage::{ Backup, Bookmark, Storage, StorageError, Stored };
+use crate::utils::timestamp_to_string;
+use crate::styles;
+
+#[derive(Clone, Debug)]
+pub enum BackupMessage {
+  Refresh,
+  Listed(Result<Vec<Backup>, StorageError>),
+  Select(PathBuf),
+  //boxed, a whole library would make every message this big
+  Selected(PathBuf, Result<Box<Stored>, StorageError>),
+  Restore,
+  Close,
+}
+
+impl BackupMessage {
+  pub fn is_save_after(message: &BackupMessage) -> bool {
+    matches!(message, BackupMessage::Restore)
+  }
+}
+
+pub struct BackupList {
+  backups: Vec<Backup>,
+  selected_path: Option<PathBuf>,
+  //none while the selected backup is still being read
+  selected: Option<Stored>,
+  error: Option<String>,
+}
+
+impl BackupList {
+  //only show this many titles per section of the diff, libraries can be big
+  const MAX_DIFF_TITLES: usize = 50;
+
+  pub fn new() -> BackupList {
+    BackupList {
+      backups: Vec::new(),
+      selected_path: None,
+      selected: None,
+      error: None,
+    }
+  }
+
+  pub fn update(&mut self, message: BackupMessage, storage: &mut Storage) {
+    match message {
+      BackupMessage::Refresh => {
+        self.selected_path = None;
+        self.selected = None;
+        self.error = None;
+      },
+      BackupMessage::Listed(Ok(backups)) => {
+        self.backups = backups;
+      },
+      BackupMessage::Select(path) => {
+        self.error = None;
+        self.selected_path = Some(path);
+        self.selected = None;
+      },
+      //ignore backups that finished reading after another one was clicked
+      BackupMessage::Selected(path, result) if self.selected_path.as_ref() == Some(&path) => {
+        match result {
+          Ok(stored) => self.selected = Some(*stored),
+          Err(error) => self.error = Some(error.to_string()),
+        }
+      },
+      BackupMessage::Listed(Err(error)) => {
+        self.error = Some(error.to_string());
+      },
+      BackupMessage::Restore => {
+        if let Some(stored) = self.selected.take() {
+          storage.replace(stored);
+          self.selected_path = None;
+        }
+      },
+      _ => {},
+    }
+  }
+
+  fn diff_section<'a>(heading: &str, bookmarks: &[&Bookmark]) -> Element<'a, BackupMessage> {
+    let mut section: Vec<Element<BackupMessage>> = vec![text(format!("{} ({})", heading, bookmarks.len())).size(20).into()];
+    for bookmark in bookmarks.iter().take(BackupList::MAX_DIFF_TITLES) {
+      section.push(text(format!("{}  {}", bookmark.title, bookmark.link)).into());
+    }
+    if bookmarks.len() > BackupList::MAX_DIFF_TITLES {
+      section.push(text(format!("...and {} more", bookmarks.len() - BackupList::MAX_DIFF_TITLES)).style(styles::BOOKMARK_TIMESTAMP_STYLE).into());
+    }
+    Column::with_children(section).spacing(3).into()
+  }
+
+  //what restoring the selected backup would do to the current library
+  fn diff_view<'a>(&self, backup: &'a Stored, current: &'a HashMap<String, Bookmark>) -> Element<'a, BackupMessage> {
+    let mut restored: Vec<&Bookmark> = backup.bookmarks.values().filter(|bookmark| !current.contains_key(&bookmark.uuid)).collect();
+    let mut removed: Vec<&Bookmark> = current.values().filter(|bookmark| !backup.bookmarks.contains_key(&bookmark.uuid)).collect();
+    let mut changed: Vec<&Bookmark> = backup.bookmarks.values().filter(|bookmark| {
+      current.get(&bookmark.uuid).map(|current_bookmark| current_bookmark != *bookmark).unwrap_or(false)
+    }).collect();
+    for bookmarks in [&mut restored, &mut removed, &mut changed] {
+      bookmarks.sort_unstable_by_key(|bookmark| Reverse(bookmark.timestamp));
+    }
+    column![
+      row![
+        text(format!("{} bookmarks", backup.bookmarks.len())).width(Length::Fill),
+        button("Restore This Backup").on_press(BackupMessage::Restore).style(theme::Button::Destructive),
+      ].align_items(Alignment::Center),
+      BackupList::diff_section("Brought back", &restored),
+      BackupList::diff_section("Removed", &removed),
+      BackupList::diff_section("Changed back", &changed),
+    ].spacing(10).into()
+  }
+
+  pub fn view<'a>(&'a self, bookmarks: &'a HashMap<String, Bookmark>) -> Element<'a, BackupMessage> {
+    let mut backup_elements: Vec<Element<BackupMessage>> = Vec::new();
+    for backup in &self.backups {
+      let is_selected: bool = self.selected_path.as_ref() == Some(&backup.path);
+      let label: String = format!("{}  ({} bookmarks)", timestamp_to_string(backup.timestamp), backup.bookmark_count);
+      backup_elements.push(
+        button(text(label)).width(Length::Fill).style(if is_selected { theme::Button::Primary } else { theme::Button::Secondary }).on_press(BackupMessage::Select(backup.path.clone())).into()
+      );
+    }
+    if self.backups.is_empty() {
+      backup_elements.push(text("No backups yet, one is made when the library is opened and then every hour while it changes").into());
+    }
+    let details: Element<BackupMessage> = if let Some(error) = &self.error {
+      text(error).into()
+    } else if let Some(stored) = &self.selected {
+      self.diff_view(stored, bookmarks)
+    } else if self.selected_path.is_some() {
+      text("Reading backup...").into()
+    } else {
+      text("Pick a backup to see what restoring it would change").into()
+    };
+    column![
+      row![
+        button("Back").on_press(BackupMessage::Close),
+        container(text("Backups").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+        button("Refresh").on_press(BackupMessage::Refresh),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      row![
+        scrollable(Column::with_children(backup_elements).spacing(5)).width(Length::FillPortion(2)),
+        scrollable(container(details).padding([0, 10])).width(Length::FillPortion(3)),
+      ].spacing(10).padding([0, 20, 10, 20]),
+    ].into()
+  }
+}
@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 
+use serde::{ Deserialize, Serialize };
+
 use iced::Element;
 use iced::{ alignment, Length };
-use iced::widget::{ button, pick_list, container, text, text_input, column, row };
+use iced::widget::{ button, pick_list, container, text, text_input, column, row, Row };
+use iced::theme;
 
-use crate::storage::{ Storage, Bookmark };
-use crate::utils::normalize_link;
+use crate::query::{ self, Field, Query };
+use crate::storage::{ SavedSearch, Storage, Bookmark };
+use crate::tags::parse_tags;
+use crate::utils::full_link;
 
 //contains bookmark search and adding
 
@@ -16,16 +21,23 @@ pub enum BarMessage {
   Hide,
   SearchOptionChange(SearchOptions),
   SortOptionChange(SortOptions),
+  SearchModeChange(SearchMode),
+  SaveSearch,
+  ApplySavedSearch(usize),
   InputSet(String, String),
   AddBookmark,
   ExpandAll,
   ShrinkAll,
-  ExportAll,
+  ShowExport,
+  ExportFormatChange(ExportFormat),
+  ExportScopeChange(ExportScope),
+  Export,
+  ShowPage(Page),
 }
 
 impl BarMessage {
   pub fn is_save_after(message: BarMessage) -> bool {
-    if message == BarMessage::AddBookmark {
+    if message == BarMessage::AddBookmark || message == BarMessage::SaveSearch {
       true
     } else {
       false
@@ -34,7 +46,7 @@ impl BarMessage {
 
   pub fn is_search_update(message: BarMessage) -> bool {
     let _s: String = "search".to_string();
-    if matches!(message, BarMessage::SearchOptionChange(_)) || matches!(message, BarMessage::SortOptionChange(_)) || matches!(message, BarMessage::InputSet(_s, _)) {
+    if matches!(message, BarMessage::SearchOptionChange(_)) || matches!(message, BarMessage::SortOptionChange(_)) || matches!(message, BarMessage::SearchModeChange(_)) || matches!(message, BarMessage::ApplySavedSearch(_)) || matches!(message, BarMessage::InputSet(_s, _)) {
       true
     } else {
       false
@@ -46,6 +58,7 @@ impl BarMessage {
 enum DisplayEnum {
   Search,
   Add,
+  Export,
   Neither,
 }
 
@@ -53,8 +66,16 @@ pub struct BookmarkBar {
   display: DisplayEnum,
   bookmark_add: BookmarkAdd,
   pub bookmark_search: BookmarkSearch,
+  pub bookmark_export: BookmarkExport,
   pub input_values: HashMap<String, String>,
   pub expand_state: bool,
+  //why the last add didn't go through
+  add_error: Option<String>,
+  //the search box, parsed. a query that doesn't parse leaves the last good one in place
+  pub query: Option<Query>,
+  search_error: Option<String>,
+  //how many bookmarks each saved search finds, in the same order as Stored.saved_searches
+  pub saved_counts: Vec<usize>,
 }
 
 impl BookmarkBar {
@@ -63,8 +84,39 @@ impl BookmarkBar {
       display: DisplayEnum::Neither,
       bookmark_add: BookmarkAdd::new(),
       bookmark_search: BookmarkSearch::new(),
+      bookmark_export: BookmarkExport::new(),
       input_values: HashMap::new(),
       expand_state: true,
+      add_error: None,
+      query: None,
+      search_error: None,
+      saved_counts: Vec::new(),
+    }
+  }
+
+  //count again what every saved search finds, after the library changed
+  pub fn refresh_saved_counts(&mut self, storage: &Storage) {
+    let Some(stored) = storage.stored.as_ref() else {
+      return;
+    };
+    self.saved_counts = stored.saved_searches.iter().map(|saved_search| {
+      match query::parse(&saved_search.query, saved_search.search_option.field()) {
+        Ok(query) => storage.index.search(query.as_ref(), None).len(),
+        Err(_) => 0,
+      }
+    }).collect();
+  }
+
+  fn parse_search(&mut self) {
+    let input: &str = self.input_values.get("search").map(|input| input.as_str()).unwrap_or_default();
+    match query::parse(input, self.bookmark_search.search_option.field()) {
+      Ok(query) => {
+        self.query = query;
+        self.search_error = None;
+      },
+      Err(error) => {
+        self.search_error = Some(error);
+      },
     }
   }
 
@@ -85,27 +137,51 @@ impl BookmarkBar {
       },
       BarMessage::ShowAdd => {
         self.display = DisplayEnum::Add;
+        self.add_error = None;
+      },
+      BarMessage::ShowExport => {
+        self.display = DisplayEnum::Export;
+      },
+      BarMessage::ExportFormatChange(new_format) => {
+        self.bookmark_export.format = new_format;
+      },
+      BarMessage::ExportScopeChange(new_scope) => {
+        self.bookmark_export.scope = new_scope;
+      },
+      BarMessage::Export => {
+        self.display = DisplayEnum::Neither;
       },
       BarMessage::Hide => {
         self.display = DisplayEnum::Neither;
       },
       BarMessage::InputSet(input_name, value) => {
+        let is_search: bool = input_name == "search";
         self.input_values.insert(input_name, value);
+        if is_search {
+          self.parse_search();
+        }
       },
       BarMessage::AddBookmark => {
         //required
         let empty_string: String = "".to_string();
         let title: String = self.input_values.get("title").unwrap_or(&empty_string).to_owned();
-        let link: String = normalize_link(self.input_values.get("link").unwrap_or(&empty_string).to_owned());
-        let already_exists = storage.stored.as_ref().unwrap().bookmarks.values().any(|bookmark| {
-          bookmark.link == link
-        });
-        if already_exists {
+        let link: String = full_link(self.input_values.get("link").unwrap_or(&empty_string));
+        if title == "".to_string() || link == "".to_string() {
+          self.add_error = Some("A bookmark needs a title and a link".to_string());
           return;
         }
-        if title == "".to_string() || link == "".to_string() {
+        if let Some(duplicate) = storage.find_duplicate(&link) {
+          self.add_error = Some(format!("Already saved as \"{}\" ({})", duplicate.title, duplicate.link));
           return;
         }
+        let tags: Vec<String> = match parse_tags(self.input_values.get("tags").unwrap_or(&empty_string), &storage.stored.as_ref().unwrap().settings) {
+          Ok(tags) => tags,
+          Err(error) => {
+            self.add_error = Some(error);
+            return;
+          },
+        };
+        self.add_error = None;
         //optional
         let mut note: Option<String> = self.input_values.get("note").cloned();
         if note.is_some() {
@@ -113,22 +189,44 @@ impl BookmarkBar {
             note = None;
           }
         }
-        let tags_value: &String = self.input_values.get("tags").unwrap_or(&empty_string);
-        let tags: Vec<String>;
-        if tags_value.trim() == &empty_string {
-          tags = Vec::new();
-        } else {
-          tags = tags_value.split(",").map(|item| item.to_string()).collect();
-        }
         storage.add_bookmark(Bookmark::new(title, link, note, tags, None));
         self.reset();
       },
       BarMessage::SearchOptionChange(new_search_option) => {
         self.bookmark_search.search_option = new_search_option;
+        self.parse_search();
       },
       BarMessage::SortOptionChange(new_sort_option) => {
         self.bookmark_search.sort_option = new_sort_option;
       },
+      BarMessage::SearchModeChange(new_search_mode) => {
+        self.bookmark_search.search_mode = new_search_mode;
+      },
+      BarMessage::SaveSearch => {
+        let search: String = self.input_values.get("search").cloned().unwrap_or_default();
+        if search.trim().is_empty() {
+          self.search_error = Some("Type a search to save it".to_string());
+          return;
+        }
+        let name: String = self.input_values.remove("saved_search_name").filter(|name| !name.trim().is_empty()).unwrap_or_else(|| search.clone());
+        let mut saved_searches: Vec<SavedSearch> = storage.stored.as_ref().unwrap().saved_searches.clone();
+        saved_searches.push(SavedSearch {
+          name: name.trim().to_string(),
+          search_option: self.bookmark_search.search_option,
+          sort_option: self.bookmark_search.sort_option,
+          query: search,
+        });
+        storage.set_saved_searches(saved_searches);
+      },
+      BarMessage::ApplySavedSearch(index) => {
+        if let Some(saved_search) = storage.stored.as_ref().unwrap().saved_searches.get(index) {
+          self.bookmark_search.search_option = saved_search.search_option;
+          self.bookmark_search.sort_option = saved_search.sort_option;
+          self.input_values.insert("search".to_string(), saved_search.query.clone());
+          self.display = DisplayEnum::Search;
+          self.parse_search();
+        }
+      },
       BarMessage::ExpandAll => {
         self.expand_state = false;
       },
@@ -139,7 +237,26 @@ impl BookmarkBar {
     }
   }
 
-  pub fn view(&self) -> Element<BarMessage> {
+  //the bar, with the saved searches under it
+  pub fn view<'a>(&'a self, saved_searches: &'a [SavedSearch]) -> Element<'a, BarMessage> {
+    if saved_searches.is_empty() {
+      return self.bar_view();
+    }
+    let mut chips: Row<BarMessage> = row![text("Saved:")].spacing(5).align_items(iced::Alignment::Center);
+    for (index, saved_search) in saved_searches.iter().enumerate() {
+      let count: usize = self.saved_counts.get(index).copied().unwrap_or_default();
+      chips = chips.push(
+        button(text(format!("{} ({})", saved_search.name, count))).padding([3, 8]).style(theme::Button::Secondary).on_press(BarMessage::ApplySavedSearch(index))
+      );
+    }
+    chips = chips.push(button("Manage").padding([3, 8]).on_press(BarMessage::ShowPage(Page::SavedSearches)));
+    column![
+      self.bar_view(),
+      container(chips).padding([0, 20, 8, 20]),
+    ].into()
+  }
+
+  fn bar_view(&self) -> Element<'_, BarMessage> {
     let expand_state_container: Element<BarMessage>;
     if self.expand_state {
       //show "Expand All"
@@ -157,9 +274,12 @@ impl BookmarkBar {
       ].spacing(5)).width(Length::Shrink).align_x(alignment::Horizontal::Left).into();
     }
 
-    let export_button: Element<BarMessage> = button(
-      text("Export All").horizontal_alignment(alignment::Horizontal::Center)
-    ).on_press(BarMessage::ExportAll).width(Length::Fixed(90.0)).into();
+    let export_button: Element<BarMessage> = row![
+      pick_list(Page::all(), None, BarMessage::ShowPage).placeholder("More...").width(Length::Fixed(110.0)),
+      button(
+        text("Export").horizontal_alignment(alignment::Horizontal::Center)
+      ).on_press(BarMessage::ShowExport).width(Length::Fixed(90.0)),
+    ].spacing(5).into();
 
     if self.display == DisplayEnum::Add {
       column![
@@ -177,7 +297,7 @@ impl BookmarkBar {
           ).width(Length::Fill).align_x(alignment::Horizontal::Center),
           export_button,
         ],
-        self.bookmark_add.view(&self.input_values),
+        self.bookmark_add.view(&self.input_values, &self.add_error),
       ].spacing(8).padding([10, 20]).into()
     } else if self.display == DisplayEnum::Search {
       column![
@@ -195,7 +315,25 @@ impl BookmarkBar {
           ).width(Length::Fill).align_x(alignment::Horizontal::Center),
           export_button,
         ],
-        self.bookmark_search.view(&self.input_values),
+        self.bookmark_search.view(&self.input_values, &self.search_error),
+      ].spacing(8).padding([10, 20]).into()
+    } else if self.display == DisplayEnum::Export {
+      column![
+        row![
+          expand_state_container,
+          container(
+            row![
+              button(
+                text("Show Search").horizontal_alignment(alignment::Horizontal::Center)
+              ).width(Length::Fixed(110.0)).on_press(BarMessage::ShowSearch),
+              button(
+                text("Hide Export").horizontal_alignment(alignment::Horizontal::Center)
+              ).width(Length::Fixed(170.0)).on_press(BarMessage::Hide),
+            ].spacing(5)
+          ).width(Length::Fill).align_x(alignment::Horizontal::Center),
+          export_button,
+        ],
+        self.bookmark_export.view(),
       ].spacing(8).padding([10, 20]).into()
     } else {
       container(
@@ -218,11 +356,11 @@ impl BookmarkBar {
   }
 }
 
-trait Options where Self: Sized {
+pub trait Options where Self: Sized {
   fn all() -> Vec<Self>;
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum SearchOptions {
   All,
   Title,
@@ -236,6 +374,18 @@ impl Options for SearchOptions {
   }
 }
 
+impl SearchOptions {
+  //what words without a field: in front search
+  pub fn field(&self) -> Field {
+    match self {
+      SearchOptions::All => Field::Any,
+      SearchOptions::Title => Field::Title,
+      SearchOptions::Link => Field::Link,
+      SearchOptions::Tags => Field::Tags,
+    }
+  }
+}
+
 impl std::fmt::Display for SearchOptions {
   fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     let content: String = match self {
@@ -249,6 +399,29 @@ impl std::fmt::Display for SearchOptions {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchMode {
+  Contains,
+  //close enough spellings match too
+  Fuzzy,
+}
+
+impl Options for SearchMode {
+  fn all() -> Vec<SearchMode> {
+    vec![SearchMode::Contains, SearchMode::Fuzzy]
+  }
+}
+
+impl std::fmt::Display for SearchMode {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      SearchMode::Contains => "Contains".to_string(),
+      SearchMode::Fuzzy => "Fuzzy".to_string(),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum SortOptions {
   Relevant,
   Newest,
@@ -272,9 +445,98 @@ impl std::fmt::Display for SortOptions {
   }
 }
 
+//the other screens, picked from the "More..." list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Page {
+  Bookmarks,
+  Import,
+  //opened by the export button rather than from the list
+  Export,
+  Trash,
+  Duplicates,
+  SavedSearches,
+  Tags,
+  Backups,
+  Settings,
+}
+
+impl Options for Page {
+  fn all() -> Vec<Page> {
+    //no need to list the page the bar is already on
+    vec![Page::Import, Page::Trash, Page::Duplicates, Page::SavedSearches, Page::Tags, Page::Backups, Page::Settings]
+  }
+}
+
+impl std::fmt::Display for Page {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      Page::Bookmarks => "Bookmarks".to_string(),
+      Page::Import => "Import".to_string(),
+      Page::Export => "Export".to_string(),
+      Page::Trash => "Trash".to_string(),
+      Page::Duplicates => "Find Duplicates".to_string(),
+      Page::SavedSearches => "Saved Searches".to_string(),
+      Page::Tags => "Manage Tags".to_string(),
+      Page::Backups => "Backups".to_string(),
+      Page::Settings => "Settings".to_string(),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+  Html,
+  Csv,
+  Markdown,
+  Json,
+}
+
+impl Options for ExportFormat {
+  fn all() -> Vec<ExportFormat> {
+    vec![ExportFormat::Html, ExportFormat::Csv, ExportFormat::Markdown, ExportFormat::Json]
+  }
+}
+
+impl std::fmt::Display for ExportFormat {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      ExportFormat::Html => "Browser HTML".to_string(),
+      ExportFormat::Csv => "CSV".to_string(),
+      ExportFormat::Markdown => "Markdown".to_string(),
+      ExportFormat::Json => "reservoir JSON".to_string(),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportScope {
+  All,
+  //only what the list is showing for the current search
+  Filtered,
+}
+
+impl Options for ExportScope {
+  fn all() -> Vec<ExportScope> {
+    vec![ExportScope::All, ExportScope::Filtered]
+  }
+}
+
+impl std::fmt::Display for ExportScope {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      ExportScope::All => "Whole Library".to_string(),
+      ExportScope::Filtered => "Search Results".to_string(),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
 pub struct BookmarkSearch {
   pub search_option: SearchOptions,
   pub sort_option: SortOptions,
+  pub search_mode: SearchMode,
 }
 
 impl BookmarkSearch {
@@ -282,16 +544,24 @@ impl BookmarkSearch {
     BookmarkSearch {
       search_option: SearchOptions::All,
       sort_option: SortOptions::Relevant,
+      search_mode: SearchMode::Contains,
     }
   }
 
-  pub fn view(&self, input_values: &HashMap<String, String>) -> Element<BarMessage> {
-    row![
+  pub fn view<'a>(&self, input_values: &HashMap<String, String>, search_error: &'a Option<String>) -> Element<'a, BarMessage> {
+    let search_row = row![
       pick_list(SearchOptions::all(), Some(self.search_option), BarMessage::SearchOptionChange),
       pick_list(SortOptions::all(), Some(self.sort_option), BarMessage::SortOptionChange),
-      text_input("Search Query", input_values.get("search").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("search".to_string(), value)),
+      pick_list(SearchMode::all(), Some(self.search_mode), BarMessage::SearchModeChange),
+      text_input("Search Query (tag:rust -tag:old site:github.com OR ...)", input_values.get("search").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("search".to_string(), value)),
       //button("Search"),
-    ].spacing(5).into()
+      text_input("Name", input_values.get("saved_search_name").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("saved_search_name".to_string(), value)).on_submit(BarMessage::SaveSearch).width(Length::Fixed(120.0)),
+      button("Save Search").on_press(BarMessage::SaveSearch),
+    ].spacing(5);
+    match search_error {
+      Some(search_error) => column![search_row, text(search_error)].spacing(5).into(),
+      None => search_row.into(),
+    }
   }
 }
 
@@ -303,13 +573,40 @@ impl BookmarkAdd {
     BookmarkAdd
   }
 
-  pub fn view(&self, input_values: &HashMap<String, String>) -> Element<BarMessage> {
-    row![
+  pub fn view<'a>(&self, input_values: &HashMap<String, String>, add_error: &'a Option<String>) -> Element<'a, BarMessage> {
+    let add_row = row![
       text_input("Title", input_values.get("title").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("title".to_string(), value)),
       text_input("Link", input_values.get("link").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("link".to_string(), value)),
       text_input("Note", input_values.get("note").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("note".to_string(), value)),
       text_input("Tags (CSV)", input_values.get("tags").unwrap_or(&"".to_string())).on_input(|value| BarMessage::InputSet("tags".to_string(), value)),
       button("Add").on_press(BarMessage::AddBookmark),
+    ].spacing(5);
+    match add_error {
+      Some(add_error) => column![add_row, text(add_error)].spacing(5).into(),
+      None => add_row.into(),
+    }
+  }
+}
+
+//picks what to export, pops up after the export button is clicked
+pub struct BookmarkExport {
+  pub format: ExportFormat,
+  pub scope: ExportScope,
+}
+
+impl BookmarkExport {
+  pub fn new() -> BookmarkExport {
+    BookmarkExport {
+      format: ExportFormat::Html,
+      scope: ExportScope::All,
+    }
+  }
+
+  pub fn view(&self) -> Element<'_, BarMessage> {
+    row![
+      pick_list(ExportFormat::all(), Some(self.format), BarMessage::ExportFormatChange),
+      pick_list(ExportScope::all(), Some(self.scope), BarMessage::ExportScopeChange),
+      button("Export").on_press(BarMessage::Export),
     ].spacing(5).into()
   }
 }
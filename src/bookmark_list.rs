@@ -1,15 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{ BTreeMap, HashMap, HashSet };
 
 use iced::Element;
 use iced::{ alignment, Alignment, Length, theme };
-use iced::widget::{ button, container, text, text_input, scrollable, row, Row, column, Column };
+use iced::widget::{ button, container, text, text_input, pick_list, scrollable, row, Row, column, Column };
 
 use webbrowser;
 
 use crate::WindowSize;
-use crate::storage::{ Bookmark, Storage };
-use crate::bookmark_bar::{ SortOptions, SearchOptions };
-use crate::utils::{ timestamp_to_string, truncate_with_ellipses, normalize_link };
+use crate::storage::{ Bookmark, Settings, Storage };
+use crate::bookmark_bar::{ Options, SearchMode, SortOptions };
+use crate::fuzzy;
+use crate::query::{ Field, Query };
+use crate::relevance;
+use crate::tags::{ self, parse_tags };
+use crate::utils::{ display_link, full_link, get_timestamp, timestamp_to_string, truncate_with_ellipses };
 use crate::styles;
 
 #[derive(Clone, Debug)]
@@ -22,8 +26,16 @@ pub enum ListMessage {
   DeleteBookmark(String),
   OpenLink(String),
   InputSet(String, String),
-  UpdateSearch(SearchOptions, SortOptions, Option<String>),
+  UpdateSearch(Option<Query>, SortOptions, SearchMode),
   TagPress(String),
+  //include, then exclude, then neither
+  ToggleTag(String),
+  TagMatchChange(TagMatch),
+  ClearTags,
+  ToggleTagSidebar,
+  //open or close a tag in the sidebar's tree
+  ToggleTagNode(String),
+  ShowMore,
   ExpandAll,
   ShrinkAll,
 }
@@ -38,35 +50,103 @@ impl ListMessage {
   }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagFilter {
+  Include,
+  Exclude,
+}
+
+//whether a bookmark needs all of the included tags or any of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagMatch {
+  All,
+  Any,
+}
+
+impl Options for TagMatch {
+  fn all() -> Vec<TagMatch> {
+    vec![TagMatch::All, TagMatch::Any]
+  }
+}
+
+impl std::fmt::Display for TagMatch {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "{}", match self {
+      TagMatch::All => "All Tags".to_string(),
+      TagMatch::Any => "Any Tag".to_string(),
+    })
+  }
+}
+
 pub struct BookmarkList {
-  filter: SearchOptions,
   sort: SortOptions,
-  query: Option<String>,
-  expand_uuids: Vec<String>,
+  mode: SearchMode,
+  query: Option<Query>,
+  //uuids of the bookmarks matching the search, in order
+  results: Vec<String>,
+  //how many of the results are rendered, big libraries would take too long to lay out all at once
+  shown_count: usize,
+  expand_uuids: HashSet<String>,
   edit_uuids: Vec<String>,
   input_values: HashMap<String, String>,
+  //why saving an edit didn't work, by uuid
+  edit_errors: HashMap<String, String>,
+  //the sidebar's tags (lowercased, like the index has them) and what they do to the results
+  tag_filter: BTreeMap<String, TagFilter>,
+  tag_match: TagMatch,
+  //every tag and every tag above one, as the sidebar's tree (parents before their children),
+  //with how many of the results are under it and whether it has children
+  tag_tree: Vec<(String, usize, bool)>,
+  //tags opened in the tree, their children are shown
+  expanded_tags: HashSet<String>,
+  show_tags: bool,
 }
 
 impl BookmarkList {
   const ITEM_PADDING: [u16; 2] = [15, 15];
+  const PAGE_SIZE: usize = 100;
 
   pub fn new() -> BookmarkList {
     BookmarkList {
-      filter: SearchOptions::All,
       sort: SortOptions::Relevant,
+      mode: SearchMode::Contains,
       query: None,
-      expand_uuids: Vec::new(),
+      results: Vec::new(),
+      shown_count: BookmarkList::PAGE_SIZE,
+      expand_uuids: HashSet::new(),
       edit_uuids: Vec::new(),
       input_values: HashMap::new(),
+      edit_errors: HashMap::new(),
+      tag_filter: BTreeMap::new(),
+      tag_match: TagMatch::All,
+      tag_tree: Vec::new(),
+      expanded_tags: HashSet::new(),
+      show_tags: true,
+    }
+  }
+
+  //the search and the tag filter together, tags are always matched exactly
+  fn filter_query(&self) -> Option<Query> {
+    let tag_query = |tag: &String| Query::Text(Field::Tag, tag.clone());
+    let included: Vec<Query> = self.tag_filter.iter().filter(|(_, filter)| **filter == TagFilter::Include).map(|(tag, _)| tag_query(tag)).collect();
+    let mut all: Vec<Query> = self.query.iter().cloned().collect();
+    match self.tag_match {
+      TagMatch::All => all.extend(included),
+      TagMatch::Any if included.len() > 1 => all.push(Query::Or(included)),
+      TagMatch::Any => all.extend(included),
+    }
+    all.extend(self.tag_filter.iter().filter(|(_, filter)| **filter == TagFilter::Exclude).map(|(tag, _)| Query::Not(Box::new(tag_query(tag)))));
+    match all.len() {
+      0 => None,
+      1 => all.pop(),
+      _ => Some(Query::And(all)),
     }
   }
 
   pub fn update(&mut self, message: ListMessage, storage: &mut Storage) {
     match message {
       ListMessage::ExpandBookmark(uuid_value) => {
-        if !self.expand_uuids.contains(&uuid_value) {
-          self.expand_uuids.push(uuid_value);
-        }
+        self.expand_uuids.insert(uuid_value);
       },
       ListMessage::UnexpandBookmark(uuid_value) => {
         self.expand_uuids.retain(|value| value != &uuid_value);
@@ -87,6 +167,7 @@ impl BookmarkList {
       },
       ListMessage::IgnoreEditBookmark(uuid_value) => {
         self.edit_uuids.retain(|value| value != &uuid_value);
+        self.edit_errors.remove(&uuid_value);
         //reset edit fields
         self.input_values.retain(|key, _| {
           !key.starts_with(&format!("{}-", uuid_value))
@@ -108,18 +189,19 @@ impl BookmarkList {
         if link_input.is_some() {
           let temp_link: String = link_input.unwrap().to_string();
           if temp_link != "".to_string() {
-            bookmark.link = normalize_link(temp_link);
+            bookmark.link = full_link(&temp_link);
           }
         }
         //tags
         let tags_input = self.input_values.get(&format!("{}-tags", &uuid_value));
-        if tags_input.is_some() {
-          let empty_string: String = "".to_string();
-          let tags_value: &String = tags_input.unwrap_or(&empty_string);
-          if tags_value.trim() == &empty_string {
-            bookmark.tags = Vec::new();
-          } else {
-            bookmark.tags = tags_value.split(",").map(|item| item.to_string()).collect();
+        if let Some(tags_value) = tags_input {
+          match parse_tags(tags_value, &storage.stored.as_ref().unwrap().settings) {
+            Ok(tags) => bookmark.tags = tags,
+            Err(error) => {
+              //stay in edit mode, so the tags can be fixed
+              self.edit_errors.insert(uuid_value, error);
+              return;
+            },
           }
         }
         //note
@@ -142,14 +224,14 @@ impl BookmarkList {
         //change value
         storage.add_bookmark(bookmark);
         self.edit_uuids.retain(|value| value != &uuid_value);
+        self.edit_errors.remove(&uuid_value);
       },
       ListMessage::DeleteBookmark(uuid_value) => {
         storage.remove_bookmark(uuid_value);
       },
       ListMessage::OpenLink(link) => {
-        let with_https: String = format!("https://{}", link);
-        let open_result = webbrowser::open(if !link.starts_with("https://") && !link.starts_with("http://") { &with_https } else { &link });
-        match open_result {
+        //the scheme was already checked against the allowed ones
+        match webbrowser::open(&link) {
           Ok(()) => {},
           Err(error) => {
             dbg!("{:?}", error);
@@ -159,83 +241,228 @@ impl BookmarkList {
       ListMessage::InputSet(input_name, value) => {
         self.input_values.insert(input_name, value);
       },
-      ListMessage::UpdateSearch(new_filter, new_sort, new_query) => {
-        self.filter = new_filter;
+      ListMessage::UpdateSearch(new_query, new_sort, new_mode) => {
         self.sort = new_sort;
+        self.mode = new_mode;
         self.query = new_query;
+        self.shown_count = BookmarkList::PAGE_SIZE;
+        self.refresh(storage);
+      },
+      ListMessage::TagPress(tag) => {
+        let tag: String = tag.to_lowercase();
+        //open the tree down to it
+        if let Some(parent) = tags::parent(&tag) {
+          self.expanded_tags.extend(tags::with_ancestors(parent).into_iter().map(|ancestor| ancestor.to_string()));
+        }
+        self.tag_filter.insert(tag, TagFilter::Include);
+        self.show_tags = true;
+        self.shown_count = BookmarkList::PAGE_SIZE;
+        self.refresh(storage);
+      },
+      ListMessage::ToggleTag(tag) => {
+        match self.tag_filter.get(&tag) {
+          None => {
+            self.tag_filter.insert(tag, TagFilter::Include);
+          },
+          Some(TagFilter::Include) => {
+            self.tag_filter.insert(tag, TagFilter::Exclude);
+          },
+          Some(TagFilter::Exclude) => {
+            self.tag_filter.remove(&tag);
+          },
+        }
+        self.shown_count = BookmarkList::PAGE_SIZE;
+        self.refresh(storage);
+      },
+      ListMessage::TagMatchChange(tag_match) => {
+        self.tag_match = tag_match;
+        self.shown_count = BookmarkList::PAGE_SIZE;
+        self.refresh(storage);
+      },
+      ListMessage::ClearTags => {
+        self.tag_filter = BTreeMap::new();
+        self.shown_count = BookmarkList::PAGE_SIZE;
+        self.refresh(storage);
+      },
+      ListMessage::ToggleTagSidebar => {
+        self.show_tags = !self.show_tags;
+      },
+      ListMessage::ToggleTagNode(tag) => {
+        if !self.expanded_tags.remove(&tag) {
+          self.expanded_tags.insert(tag);
+        }
+      },
+      ListMessage::ShowMore => {
+        self.shown_count += BookmarkList::PAGE_SIZE;
       },
       ListMessage::ExpandAll => {
         //this unwrap() is safe I think, since you can't have the bookmark list before it's loaded
         self.expand_uuids = storage.stored.as_ref().unwrap().bookmarks.values().map(|bookmark: &Bookmark| bookmark.uuid.clone()).collect();
       },
       ListMessage::ShrinkAll => {
-        self.expand_uuids = Vec::new();
+        self.expand_uuids = HashSet::new();
       },
-      _ => {},
     }
   }
 
-  pub fn view(&self, bookmarks: &HashMap<String, Bookmark>, window_size: &WindowSize) -> Element<ListMessage> {
-    let mut bookmarks_show: Vec<&Bookmark> = bookmarks.values().collect();
-    //filter stuff
-    if self.query.is_some() {
-      let query: String = self.query.as_ref().unwrap().to_string().to_lowercase();
-      match self.filter {
-        SearchOptions::All => {
-          bookmarks_show.retain(|bookmark| {
-            if bookmark.link.to_lowercase().contains(&query) || bookmark.title.to_lowercase().contains(&query) || bookmark.tags.iter().map(|tag| tag.to_lowercase()).any(|item| item.contains(&query)) || bookmark.note.as_ref().unwrap_or(&"".to_string()).to_lowercase().contains(&query) {
-              true
-            } else {
-              false
-            }
-          });
-        },
-        SearchOptions::Title => {
-          bookmarks_show.retain(|bookmark| {
-            if bookmark.title.to_lowercase().contains(&query) {
-              true
-            } else {
-              false
-            }
-          });
-        },
-        SearchOptions::Link => {
-          bookmarks_show.retain(|bookmark| {
-            if bookmark.link.to_lowercase().contains(&query) {
-              true
-            } else {
-              false
-            }
-          });
-        },
-        SearchOptions::Tags => {
-          bookmarks_show.retain(|bookmark| {
-            if bookmark.tags.iter().map(|tag| tag.to_lowercase()).any(|item| item.contains(&query)) {
-              true
-            } else {
-              false
-            }
-          });
-        },
-      }
-    }
-    //sort stuff
+  //search the index again and sort what it found. called whenever the search or the library changes,
+  //so rendering only has to look up the bookmarks on the current page
+  pub fn refresh(&mut self, storage: &Storage) {
+    let Some(stored) = storage.stored.as_ref() else {
+      return;
+    };
+    let fuzzy_similarity: Option<f64> = self.fuzzy_similarity(&stored.settings);
+    let mut bookmarks_show: Vec<&Bookmark> = storage.index.search(self.filter_query().as_ref(), fuzzy_similarity).iter().filter_map(|uuid| stored.bookmarks.get(uuid)).collect();
+    //sort stuff. ties go to the newest, then by uuid, so the order never changes between renders
     match self.sort {
+      SortOptions::Relevant => {
+        let now: u64 = get_timestamp();
+        let mut scored: Vec<(f64, &Bookmark)> = bookmarks_show.into_iter().map(|bookmark| (relevance::score(self.query.as_ref(), bookmark, now, fuzzy_similarity), bookmark)).collect();
+        scored.sort_unstable_by(|(score1, bookmark1), (score2, bookmark2)| {
+          score2.total_cmp(score1).then_with(|| bookmark2.timestamp.cmp(&bookmark1.timestamp)).then_with(|| bookmark1.uuid.cmp(&bookmark2.uuid))
+        });
+        bookmarks_show = scored.into_iter().map(|(_, bookmark)| bookmark).collect();
+      },
       SortOptions::Newest => {
         bookmarks_show.sort_unstable_by(|bookmark1: &&Bookmark, bookmark2: &&Bookmark| {
-          bookmark2.timestamp.cmp(&bookmark1.timestamp)
+          bookmark2.timestamp.cmp(&bookmark1.timestamp).then_with(|| bookmark1.uuid.cmp(&bookmark2.uuid))
         });
       },
       SortOptions::Oldest => {
         bookmarks_show.sort_unstable_by(|bookmark1: &&Bookmark, bookmark2: &&Bookmark| {
-          bookmark1.timestamp.cmp(&bookmark2.timestamp)
+          bookmark1.timestamp.cmp(&bookmark2.timestamp).then_with(|| bookmark1.uuid.cmp(&bookmark2.uuid))
         });
       },
-      _ => {},
     }
+    self.refresh_tag_tree(storage, &bookmarks_show);
+    self.results = bookmarks_show.into_iter().map(|bookmark| bookmark.uuid.clone()).collect();
+  }
+
+  //tag counts within the results, so they narrow along with them. a bookmark counts once for every tag it is under
+  fn refresh_tag_tree(&mut self, storage: &Storage, bookmarks_show: &[&Bookmark]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    //tags in the filter stay listed after their last bookmark is gone, so they can be turned off
+    for tag in storage.index.tag_names().chain(self.tag_filter.keys()) {
+      for ancestor in tags::with_ancestors(tag) {
+        counts.entry(ancestor.to_string()).or_default();
+      }
+    }
+    for bookmark in bookmarks_show {
+      let lowercased: Vec<String> = bookmark.tags.iter().map(|tag| tag.to_lowercase()).collect();
+      let under: HashSet<&str> = lowercased.iter().flat_map(|tag| tags::with_ancestors(tag)).collect();
+      for tag in under {
+        *counts.entry(tag.to_string()).or_default() += 1;
+      }
+    }
+    let mut children: HashMap<&str, Vec<(&String, usize)>> = HashMap::new();
+    for (tag, count) in &counts {
+      children.entry(tags::parent(tag).unwrap_or("")).or_default().push((tag, *count));
+    }
+    for siblings in children.values_mut() {
+      siblings.sort_unstable_by(|(tag1, count1), (tag2, count2)| count2.cmp(count1).then_with(|| tag1.cmp(tag2)));
+    }
+    //depth first, so every tag comes right after its parent
+    let mut tag_tree: Vec<(String, usize, bool)> = Vec::new();
+    let mut stack: Vec<(&String, usize)> = children.get("").map(|roots| roots.iter().rev().cloned().collect()).unwrap_or_default();
+    while let Some((tag, count)) = stack.pop() {
+      let tag_children: Option<&Vec<(&String, usize)>> = children.get(tag.as_str());
+      tag_tree.push((tag.clone(), count, tag_children.is_some()));
+      stack.extend(tag_children.into_iter().flat_map(|tag_children| tag_children.iter().rev().cloned()));
+    }
+    self.tag_tree = tag_tree;
+  }
+
+  //the tags to filter by, with include/exclude toggles and counts
+  fn tag_sidebar(&self) -> Element<'_, ListMessage> {
+    if !self.show_tags {
+      return container(button("Tags").on_press(ListMessage::ToggleTagSidebar)).padding([10, 0, 10, 20]).into();
+    }
+    let mut tag_elements: Vec<Element<ListMessage>> = Vec::new();
+    for (tag, count, has_children) in &self.tag_tree {
+      //only tags whose parents are all open
+      let ancestors: Vec<&str> = tags::with_ancestors(tag);
+      if !ancestors[..ancestors.len() - 1].iter().all(|ancestor| self.expanded_tags.contains(*ancestor)) {
+        continue;
+      }
+      let leaf: &str = tags::leaf(tag);
+      let label: String = match self.tag_filter.get(tag) {
+        Some(TagFilter::Include) => format!("+ {} ({})", leaf, count),
+        Some(TagFilter::Exclude) => format!("- {} ({})", leaf, count),
+        None => format!("{} ({})", leaf, count),
+      };
+      let style: theme::Button = match self.tag_filter.get(tag) {
+        Some(TagFilter::Exclude) => theme::Button::Destructive,
+        Some(TagFilter::Include) => theme::Button::Custom(Box::new(styles::TagButton { text: tag.to_string() })),
+        None if *count == 0 => theme::Button::Secondary,
+        None => theme::Button::Custom(Box::new(styles::TagButton { text: tag.to_string() })),
+      };
+      let expand_button = if *has_children {
+        button(text(if self.expanded_tags.contains(tag) { "v" } else { ">" })).padding([3, 6]).style(theme::Button::Text).on_press(ListMessage::ToggleTagNode(tag.clone()))
+      } else {
+        button(text(" ")).padding([3, 6]).style(theme::Button::Text)
+      };
+      tag_elements.push(row![
+        expand_button,
+        button(text(label)).padding([3, 6]).style(style).on_press(ListMessage::ToggleTag(tag.clone())),
+      ].padding([0, 0, 0, 12 * tags::depth(tag) as u16]).align_items(Alignment::Center).into());
+    }
+    if self.tag_tree.is_empty() {
+      tag_elements.push(text("No tags").style(styles::BOOKMARK_TIMESTAMP_STYLE).into());
+    }
+    let mut clear_button = button("Clear");
+    if !self.tag_filter.is_empty() {
+      clear_button = clear_button.on_press(ListMessage::ClearTags);
+    }
+    column![
+      row![
+        button("Hide").on_press(ListMessage::ToggleTagSidebar),
+        clear_button,
+      ].spacing(5),
+      pick_list(TagMatch::all(), Some(self.tag_match), ListMessage::TagMatchChange),
+      text("Click a tag to include it, again to exclude it. Tags under it are included too").style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      scrollable(Column::with_children(tag_elements).spacing(5)),
+    ].spacing(8).width(Length::Fixed(200.0)).padding([10, 0, 10, 20]).into()
+  }
+
+  //the bookmarks matching the current search, in the order they are shown
+  pub fn shown<'a>(&self, bookmarks: &'a HashMap<String, Bookmark>) -> Vec<&'a Bookmark> {
+    self.results.iter().filter_map(|uuid| bookmarks.get(uuid)).collect()
+  }
+
+  //the lowest similarity that counts as a match, none unless searching fuzzily
+  fn fuzzy_similarity(&self, settings: &Settings) -> Option<f64> {
+    match self.mode {
+      SearchMode::Contains => None,
+      SearchMode::Fuzzy => Some(settings.fuzzy_similarity.min(100) as f64 / 100.0),
+    }
+  }
+
+  //text with whatever the search matched in it picked out. fields are the search fields that look at this text
+  fn highlighted<'a>(&self, content: &str, fields: &[Field], settings: &Settings) -> Element<'a, ListMessage> {
+    let minimum: f64 = self.fuzzy_similarity(settings).unwrap_or(1.0);
+    let mut spans: Vec<fuzzy::Span> = Vec::new();
+    for (field, term) in self.query.iter().flat_map(Query::terms) {
+      if field == Field::Any || fields.contains(&field) {
+        let (similarity, term_spans) = fuzzy::fuzzy_match(content, term);
+        if similarity >= minimum {
+          spans.extend(term_spans);
+        }
+      }
+    }
+    if spans.is_empty() {
+      return text(content).into();
+    }
+    let mut pieces: Row<ListMessage> = Row::new();
+    for (piece, highlighted) in fuzzy::split_highlighted(content, &spans) {
+      pieces = pieces.push(if highlighted { text(piece).style(styles::SEARCH_HIGHLIGHT_STYLE) } else { text(piece) });
+    }
+    pieces.into()
+  }
+
+  pub fn view(&self, bookmarks: &HashMap<String, Bookmark>, settings: &Settings, window_size: &WindowSize) -> Element<ListMessage> {
     //now display
     let mut bookmark_elements: Vec<Element<ListMessage>> = Vec::new();
-    for bookmark in bookmarks_show {
+    for bookmark in self.results.iter().take(self.shown_count).filter_map(|uuid| bookmarks.get(uuid)) {
       if self.expand_uuids.contains(&bookmark.uuid) {
         let mut timestamp_tag_row = Row::new();
         timestamp_tag_row = timestamp_tag_row.push(text(&timestamp_to_string(bookmark.timestamp)).style(styles::BOOKMARK_TIMESTAMP_STYLE));
@@ -251,8 +478,8 @@ impl BookmarkList {
             column![
               row![
                 row![
-                  container(text(&bookmark.title)).max_width(window_size.width as u16/2-60),
-                  text(&bookmark.link),
+                  container(self.highlighted(&bookmark.title, &[Field::Title], settings)).max_width(window_size.width as u16/2-60),
+                  self.highlighted(display_link(&bookmark.link), &[Field::Link, Field::Site], settings),
                 ].width(Length::FillPortion(4)).spacing(5),
                 container(row![
                   button("Unexpand").on_press(ListMessage::UnexpandBookmark(bookmark.uuid.clone())),
@@ -273,28 +500,32 @@ impl BookmarkList {
         let tags_key: String = format!("{}-tags", &bookmark.uuid);
         let note_key: String = format!("{}-note", &bookmark.uuid);
         let timestamp_key: String = format!("{}-timestamp", &bookmark.uuid);
+        let mut edit_column: Column<ListMessage> = column![
+          row![
+            row![
+              text_input("Title", self.input_values.get(&title_key).unwrap_or(&bookmark.title)).on_input(move |value| ListMessage::InputSet(title_key.clone(), value)),
+              text_input("Link", self.input_values.get(&link_key).unwrap_or(&bookmark.link)).on_input(move |value| ListMessage::InputSet(link_key.clone(), value)),
+            ].width(Length::FillPortion(4)).spacing(5),
+            container(row![
+              button("Cancel Edit").on_press(ListMessage::IgnoreEditBookmark(bookmark.uuid.clone())),
+              button("Save").on_press(ListMessage::SaveEditBookmark(bookmark.uuid.clone(), bookmark.clone())),
+              button("Delete").on_press(ListMessage::DeleteBookmark(bookmark.uuid.clone())).style(theme::Button::Destructive),
+            ].width(Length::FillPortion(1)).spacing(5)).align_x(alignment::Horizontal::Right),
+          ].align_items(Alignment::Center).spacing(5),
+          row![
+            text_input("Timestamp", self.input_values.get(&timestamp_key).unwrap_or(&bookmark.timestamp.to_string())).width(Length::FillPortion(1)).on_input(move |value| ListMessage::InputSet(timestamp_key.clone(), value)),
+            text_input("Tags", self.input_values.get(&tags_key).unwrap_or(&bookmark.tags.join(","))).width(Length::FillPortion(3)).on_input(move |value| ListMessage::InputSet(tags_key.clone(), value)),
+          ].spacing(5),
+          row![
+            text_input("Note", self.input_values.get(&note_key).unwrap_or(&bookmark.note.as_ref().unwrap_or(&"".to_string()))).on_input(move |value| ListMessage::InputSet(note_key.clone(), value)),
+          ]
+        ].spacing(5);
+        if let Some(edit_error) = self.edit_errors.get(&bookmark.uuid) {
+          edit_column = edit_column.push(text(edit_error));
+        }
         bookmark_elements.push(
           container(
-            column![
-              row![
-                row![
-                  text_input("Title", self.input_values.get(&title_key).unwrap_or(&bookmark.title)).on_input(move |value| ListMessage::InputSet(title_key.clone(), value)),
-                  text_input("Link", self.input_values.get(&link_key).unwrap_or(&bookmark.link)).on_input(move |value| ListMessage::InputSet(link_key.clone(), value)),
-                ].width(Length::FillPortion(4)).spacing(5),
-                container(row![
-                  button("Cancel Edit").on_press(ListMessage::IgnoreEditBookmark(bookmark.uuid.clone())),
-                  button("Save").on_press(ListMessage::SaveEditBookmark(bookmark.uuid.clone(), bookmark.clone())),
-                  button("Delete").on_press(ListMessage::DeleteBookmark(bookmark.uuid.clone())).style(theme::Button::Destructive),
-                ].width(Length::FillPortion(1)).spacing(5)).align_x(alignment::Horizontal::Right),
-              ].align_items(Alignment::Center).spacing(5),
-              row![
-                text_input("Timestamp", self.input_values.get(&timestamp_key).unwrap_or(&bookmark.timestamp.to_string())).width(Length::FillPortion(1)).on_input(move |value| ListMessage::InputSet(timestamp_key.clone(), value)),
-                text_input("Tags", self.input_values.get(&tags_key).unwrap_or(&bookmark.tags.join(","))).width(Length::FillPortion(3)).on_input(move |value| ListMessage::InputSet(tags_key.clone(), value)),
-              ].spacing(5),
-              row![
-                text_input("Note", self.input_values.get(&note_key).unwrap_or(&bookmark.note.as_ref().unwrap_or(&"".to_string()))).on_input(move |value| ListMessage::InputSet(note_key.clone(), value)),
-              ]
-            ].spacing(5)
+            edit_column
           ).padding(BookmarkList::ITEM_PADDING).style(theme::Container::Custom(Box::new(styles::BookmarkContainer))).into()
         );
       } else {
@@ -314,13 +545,13 @@ impl BookmarkList {
           _ => 90,
         };
         let bookmark_title: String = truncate_with_ellipses(&bookmark.title, max_title_length);
-        let bookmark_link: String = truncate_with_ellipses(&bookmark.link, max_link_length);
+        let bookmark_link: String = truncate_with_ellipses(display_link(&bookmark.link), max_link_length);
         bookmark_elements.push(
           container(
             row![
               row![
-                text(bookmark_title),
-                text(bookmark_link),
+                self.highlighted(&bookmark_title, &[Field::Title], settings),
+                self.highlighted(&bookmark_link, &[Field::Link, Field::Site], settings),
               ].width(Length::FillPortion(4)).spacing(5),
               container(row![
                 button("Expand").on_press(ListMessage::ExpandBookmark(bookmark.uuid.clone())),
@@ -332,6 +563,16 @@ impl BookmarkList {
         );
       }
     }
-    scrollable(container(Column::with_children(bookmark_elements).spacing(10)).padding([10, 20])).into()
+    if self.results.len() > self.shown_count {
+      bookmark_elements.push(
+        container(
+          button(text(format!("Show More ({} left)", self.results.len() - self.shown_count))).on_press(ListMessage::ShowMore)
+        ).width(Length::Fill).align_x(alignment::Horizontal::Center).into()
+      );
+    }
+    row![
+      self.tag_sidebar(),
+      scrollable(container(Column::with_children(bookmark_elements).spacing(10)).padding([10, 20])),
+    ].into()
   }
 }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, text, scrollable, row, column, Column };
+
+use crate::storage::{ Bookmark, Storage };
+use crate::utils::timestamp_to_string;
+use crate::styles;
+use crate::tags::normalize_tags;
+
+#[derive(Clone, Debug)]
+pub enum DuplicateMessage {
+  //merge the group with this canonical link
+  Merge(String),
+  MergeAll,
+  Close,
+}
+
+impl DuplicateMessage {
+  pub fn is_save_after(message: &DuplicateMessage) -> bool {
+    !matches!(message, DuplicateMessage::Close)
+  }
+}
+
+pub struct DuplicateList {
+  //worked out when the page is opened and when the library changes while it is open, not on every redraw
+  groups: Vec<(String, Vec<Bookmark>)>,
+}
+
+impl DuplicateList {
+  pub fn new() -> DuplicateList {
+    DuplicateList {
+      groups: Vec::new(),
+    }
+  }
+
+  //bookmarks that point to the same page, by canonical link. oldest first in each group, groups sorted by link
+  fn groups(storage: &Storage) -> Vec<(String, Vec<&Bookmark>)> {
+    let mut by_link: HashMap<String, Vec<&Bookmark>> = HashMap::new();
+    for bookmark in storage.stored.as_ref().unwrap().bookmarks.values() {
+      by_link.entry(storage.canonical_link(&bookmark.link)).or_default().push(bookmark);
+    }
+    let mut groups: Vec<(String, Vec<&Bookmark>)> = by_link.into_iter().filter(|(_, group)| group.len() > 1).collect();
+    for (_, group) in groups.iter_mut() {
+      group.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.uuid.cmp(&b.uuid)));
+    }
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+  }
+
+  pub fn refresh(&mut self, storage: &Storage) {
+    self.groups = DuplicateList::groups(storage).into_iter().map(|(link, group)| (link, group.into_iter().cloned().collect())).collect();
+  }
+
+  //keeps the oldest bookmark, with every tag and note from the group. the rest go to the trash
+  fn merge_group(storage: &mut Storage, group: Vec<Bookmark>) {
+    let mut group = group.into_iter();
+    let Some(mut kept) = group.next() else {
+      return;
+    };
+    let mut notes: Vec<String> = kept.note.iter().cloned().collect();
+    let mut removed: Vec<String> = Vec::new();
+    for other in group {
+      kept.tags.extend(other.tags);
+      if let Some(note) = other.note {
+        if !notes.contains(&note) {
+          notes.push(note);
+        }
+      }
+      removed.push(other.uuid);
+    }
+    kept.tags = normalize_tags(kept.tags, storage.stored.as_ref().unwrap().settings.lowercase_tags);
+    kept.note = if notes.is_empty() { None } else { Some(notes.join("\n\n")) };
+    storage.add_bookmark(kept);
+    for uuid in removed {
+      storage.remove_bookmark(uuid);
+    }
+  }
+
+  pub fn update(&mut self, message: DuplicateMessage, storage: &mut Storage) {
+    let groups: Vec<Vec<Bookmark>> = self.groups.iter().filter(|(link, _)| {
+      match &message {
+        DuplicateMessage::Merge(merge_link) => link == merge_link,
+        DuplicateMessage::MergeAll => true,
+        DuplicateMessage::Close => false,
+      }
+    }).map(|(_, group)| group.clone()).collect();
+    for group in groups {
+      DuplicateList::merge_group(storage, group);
+    }
+  }
+
+  pub fn view(&self) -> Element<'_, DuplicateMessage> {
+    let mut group_elements: Vec<Element<DuplicateMessage>> = Vec::new();
+    for (link, group) in &self.groups {
+      let mut group_column: Column<DuplicateMessage> = column![
+        row![
+          text(format!("{} bookmarks for {}", group.len(), link)).width(Length::Fill),
+          button("Merge").on_press(DuplicateMessage::Merge(link.clone())),
+        ].spacing(5).align_items(Alignment::Center),
+      ].spacing(5);
+      for bookmark in group {
+        let mut details: String = format!("{}  {}", timestamp_to_string(bookmark.timestamp), bookmark.link);
+        if !bookmark.tags.is_empty() {
+          details.push_str(&format!("  [{}]", bookmark.tags.join(", ")));
+        }
+        group_column = group_column.push(column![
+          text(&bookmark.title),
+          text(details).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+        ]);
+      }
+      group_elements.push(container(group_column).style(theme::Container::Custom(Box::new(styles::BookmarkContainer))).padding(10).into());
+    }
+    if self.groups.is_empty() {
+      group_elements.push(text("No duplicates found").into());
+    } else {
+      group_elements.insert(0, text("Merging keeps the oldest bookmark with all the tags and notes, and moves the others to the trash").style(styles::BOOKMARK_TIMESTAMP_STYLE).into());
+    }
+    let mut merge_all_button = button("Merge All");
+    if !self.groups.is_empty() {
+      merge_all_button = merge_all_button.on_press(DuplicateMessage::MergeAll);
+    }
+    column![
+      row![
+        button("Back").on_press(DuplicateMessage::Close),
+        container(text("Duplicates").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+        merge_all_button,
+      ].align_items(Alignment::Center).padding([10, 20]),
+      scrollable(container(Column::with_children(group_elements).spacing(10)).padding([0, 20, 10, 20])),
+    ].into()
+  }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use crate::bookmark_bar::ExportFormat;
+use crate::storage::{ Bookmark, Stored };
+
+//turns a library (or the part of it being exported) into the chosen file format
+
+fn escape_html(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+//square brackets would end the link text early, and < would start inline html
+fn escape_markdown(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]").replace('<', "\\<")
+}
+
+//newest first, so exports of the same library are always in the same order
+fn sorted(stored: &Stored) -> Vec<&Bookmark> {
+  let mut bookmarks: Vec<&Bookmark> = stored.bookmarks.values().collect();
+  bookmarks.sort_by(|bookmark1, bookmark2| bookmark2.timestamp.cmp(&bookmark1.timestamp).then_with(|| bookmark1.uuid.cmp(&bookmark2.uuid)));
+  bookmarks
+}
+
+//the format browsers export to and import from
+fn to_netscape_html(stored: &Stored) -> String {
+  let mut html: String = "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n<TITLE>Bookmarks</TITLE>\n<H1>Bookmarks</H1>\n<DL><p>\n".to_string();
+  for bookmark in sorted(stored) {
+    html.push_str(&format!("    <DT><A HREF=\"{}\" ADD_DATE=\"{}\"", escape_html(&bookmark.link), bookmark.timestamp));
+    if !bookmark.tags.is_empty() {
+      html.push_str(&format!(" TAGS=\"{}\"", escape_html(&bookmark.tags.join(","))));
+    }
+    html.push_str(&format!(">{}</A>\n", escape_html(&bookmark.title)));
+    if let Some(note) = &bookmark.note {
+      html.push_str(&format!("    <DD>{}\n", escape_html(note)));
+    }
+  }
+  html.push_str("</DL><p>\n");
+  html
+}
+
+//columns are always title, link, note, tags, timestamp, uuid. tags are comma separated inside their column
+fn to_csv(stored: &Stored) -> String {
+  let mut writer = csv::Writer::from_writer(Vec::new());
+  writer.write_record(["title", "link", "note", "tags", "timestamp", "uuid"]).unwrap();
+  for bookmark in sorted(stored) {
+    writer.write_record([
+      bookmark.title.as_str(),
+      &bookmark.link,
+      bookmark.note.as_deref().unwrap_or(""),
+      &bookmark.tags.join(","),
+      &bookmark.timestamp.to_string(),
+      bookmark.uuid.as_str(),
+    ]).unwrap();
+  }
+  //writing to a vec can't fail
+  String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+//one section per tag, so bookmarks with several tags show up more than once
+fn to_markdown(stored: &Stored) -> String {
+  let mut by_tag: BTreeMap<&str, Vec<&Bookmark>> = BTreeMap::new();
+  let mut untagged: Vec<&Bookmark> = Vec::new();
+  for bookmark in sorted(stored) {
+    if bookmark.tags.is_empty() {
+      untagged.push(bookmark);
+    }
+    for tag in &bookmark.tags {
+      by_tag.entry(tag.as_str()).or_default().push(bookmark);
+    }
+  }
+  let mut markdown: String = "# Bookmarks\n".to_string();
+  let mut sections: Vec<(&str, Vec<&Bookmark>)> = by_tag.into_iter().collect();
+  if !untagged.is_empty() {
+    sections.push(("Untagged", untagged));
+  }
+  for (heading, bookmarks) in sections {
+    markdown.push_str(&format!("\n## {}\n\n", heading));
+    for bookmark in bookmarks {
+      markdown.push_str(&format!("- [{}](<{}>)", escape_markdown(&bookmark.title), bookmark.link.replace('>', "%3E")));
+      if let Some(note) = &bookmark.note {
+        markdown.push_str(&format!(" - {}", note.replace('\n', " ")));
+      }
+      markdown.push('\n');
+    }
+  }
+  markdown
+}
+
+pub fn render(stored: &Stored, format: ExportFormat) -> String {
+  match format {
+    ExportFormat::Json => serde_json::to_string_pretty(stored).unwrap(),
+    ExportFormat::Html => to_netscape_html(stored),
+    ExportFormat::Csv => to_csv(stored),
+    ExportFormat::Markdown => to_markdown(stored),
+  }
+}
+
+pub fn extension(format: ExportFormat) -> &'static str {
+  match format {
+    ExportFormat::Json => "json",
+    ExportFormat::Html => "html",
+    ExportFormat::Csv => "csv",
+    ExportFormat::Markdown => "md",
+  }
+}
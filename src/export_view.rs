@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length };
+use iced::widget::{ button, container, text, row, column };
+
+use crate::bookmark_bar::ExportFormat;
+use crate::export;
+use crate::file_chooser::{ ChooserMessage, FileChooser };
+use crate::storage::{ Storage, StorageError };
+
+#[derive(Clone, Debug)]
+pub enum ExportMessage {
+  Chooser(ChooserMessage),
+  Done(Result<PathBuf, StorageError>),
+  Close,
+}
+
+//where to write an export, opened by the export button in the bar
+pub struct ExportView {
+  file_chooser: FileChooser,
+  exporting: bool,
+  error: Option<String>,
+}
+
+impl ExportView {
+  pub fn new() -> ExportView {
+    ExportView {
+      file_chooser: FileChooser::new(Vec::new(), "Save"),
+      exporting: false,
+      error: None,
+    }
+  }
+
+  pub fn open(&mut self, dir: PathBuf, format: ExportFormat) {
+    self.file_chooser.start_save(dir, Storage::export_file_name(format), export::extension(format));
+    self.exporting = false;
+    self.error = None;
+  }
+
+  pub fn update(&mut self, message: ExportMessage, _storage: &mut Storage) {
+    match message {
+      ExportMessage::Chooser(chooser_message) => {
+        if self.file_chooser.chosen(&chooser_message).is_some() {
+          self.exporting = true;
+          self.error = None;
+        }
+        self.file_chooser.update(chooser_message);
+      },
+      ExportMessage::Done(result) => {
+        self.exporting = false;
+        if let Err(error) = result {
+          self.error = Some(error.to_string());
+        }
+      },
+      _ => {},
+    }
+  }
+
+  //the file to write, if this message picked one
+  pub fn chosen(&self, message: &ExportMessage) -> Option<PathBuf> {
+    match message {
+      ExportMessage::Chooser(chooser_message) => self.file_chooser.chosen(chooser_message),
+      _ => None,
+    }
+  }
+
+  pub fn view(&self) -> Element<'_, ExportMessage> {
+    let mut export_column = column![].spacing(10);
+    if let Some(error) = &self.error {
+      export_column = export_column.push(text(error));
+    }
+    if self.exporting {
+      export_column = export_column.push(text("Exporting..."));
+    } else {
+      export_column = export_column.push(self.file_chooser.view().map(ExportMessage::Chooser));
+    }
+    column![
+      row![
+        button("Back").on_press(ExportMessage::Close),
+        container(text("Export").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      container(export_column).padding([0, 20, 10, 20]),
+    ].into()
+  }
+}
@@ -0,0 +1,185 @@
+use std::fs::read_dir;
+use std::path::{ Path, PathBuf };
+
+use iced::Element;
+use iced::{ Alignment, Length, theme };
+use iced::widget::{ button, checkbox, text, text_input, scrollable, row, column, Column };
+
+use crate::styles;
+
+//a small file picker drawn inside the window, used by the pages that read or write files.
+//in save mode the path box holds the file to write, and clicking a folder keeps the file name
+
+#[derive(Clone, Debug)]
+pub enum ChooserMessage {
+  Navigate(PathBuf),
+  Up,
+  PathInput(String),
+  //a file was clicked, or a path typed in. typing a folder just navigates there
+  Choose(PathBuf),
+  ShowHiddenToggle(bool),
+}
+
+pub struct FileChooser {
+  dir: PathBuf,
+  //folders first, then files with one of the extensions
+  entries: Vec<PathBuf>,
+  path_input: String,
+  //also matches whole file names, for files that don't have an extension
+  extensions: Vec<&'static str>,
+  choose_label: &'static str,
+  //the name of the file being saved, none when opening a file
+  save_name: Option<String>,
+  //dot files and folders, where browsers keep their profiles (~/.mozilla, ~/.config)
+  show_hidden: bool,
+  error: Option<String>,
+}
+
+impl FileChooser {
+  pub fn new(extensions: Vec<&'static str>, choose_label: &'static str) -> FileChooser {
+    let dir: PathBuf = directories_next::UserDirs::new().map(|user_dirs| {
+      user_dirs.download_dir().unwrap_or(user_dirs.home_dir()).to_path_buf()
+    }).unwrap_or_else(|| PathBuf::from("."));
+    let mut file_chooser: FileChooser = FileChooser {
+      dir: PathBuf::new(),
+      entries: Vec::new(),
+      path_input: String::new(),
+      extensions,
+      choose_label,
+      save_name: None,
+      show_hidden: false,
+      error: None,
+    };
+    file_chooser.navigate(dir);
+    file_chooser
+  }
+
+  //switch to picking where to write a file, starting at dir
+  pub fn start_save(&mut self, dir: PathBuf, save_name: String, extension: &'static str) {
+    self.extensions = vec![extension];
+    self.save_name = Some(save_name);
+    self.navigate(dir);
+  }
+
+  pub fn set_extensions(&mut self, extensions: Vec<&'static str>) {
+    self.extensions = extensions;
+    self.navigate(self.dir.clone());
+  }
+
+  //the file a message picks, if it picks one. when saving it only has to be in a folder that exists
+  pub fn chosen(&self, message: &ChooserMessage) -> Option<PathBuf> {
+    match message {
+      ChooserMessage::Choose(path) if self.save_name.is_none() && path.is_file() => Some(path.clone()),
+      ChooserMessage::Choose(path) if self.save_name.is_some() && !path.is_dir() && path.parent().map(Path::is_dir).unwrap_or(false) => Some(path.clone()),
+      _ => None,
+    }
+  }
+
+  fn matches_extension(&self, path: &Path) -> bool {
+    let extension: &str = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    let file_name: &str = path.file_name().and_then(|file_name| file_name.to_str()).unwrap_or_default();
+    self.extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(extension) || *wanted == file_name)
+  }
+
+  fn navigate(&mut self, dir: PathBuf) {
+    match read_dir(&dir) {
+      Ok(read) => {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        let mut files: Vec<PathBuf> = Vec::new();
+        for entry in read.flatten() {
+          let path: PathBuf = entry.path();
+          //hidden unless asked for, like a normal file picker
+          if !self.show_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+          }
+          if path.is_dir() {
+            dirs.push(path);
+          } else if self.matches_extension(&path) {
+            files.push(path);
+          }
+        }
+        dirs.sort();
+        files.sort();
+        dirs.append(&mut files);
+        self.entries = dirs;
+        self.path_input = match &self.save_name {
+          Some(save_name) => dir.join(save_name).display().to_string(),
+          None => dir.display().to_string(),
+        };
+        self.dir = dir;
+        self.error = None;
+      },
+      Err(error) => {
+        self.error = Some(format!("Could not open {}: {}", dir.display(), error));
+      },
+    }
+  }
+
+  pub fn update(&mut self, message: ChooserMessage) {
+    match message {
+      ChooserMessage::Navigate(dir) => {
+        self.navigate(dir);
+      },
+      ChooserMessage::Up => {
+        if let Some(parent) = self.dir.parent() {
+          self.navigate(parent.to_path_buf());
+        }
+      },
+      ChooserMessage::PathInput(input) => {
+        //keep the file name typed in for when another folder is clicked
+        if self.save_name.is_some() {
+          if let Some(file_name) = Path::new(&input).file_name() {
+            self.save_name = Some(file_name.to_string_lossy().to_string());
+          }
+        }
+        self.path_input = input;
+      },
+      ChooserMessage::ShowHiddenToggle(show_hidden) => {
+        self.show_hidden = show_hidden;
+        self.navigate(self.dir.clone());
+      },
+      ChooserMessage::Choose(path) => {
+        if path.is_dir() {
+          self.navigate(path);
+        } else if self.chosen(&ChooserMessage::Choose(path.clone())).is_some() {
+          self.error = None;
+        } else if self.save_name.is_some() {
+          self.error = Some(format!("The folder for {} does not exist", path.display()));
+        } else {
+          self.error = Some(format!("{} does not exist", path.display()));
+        }
+      },
+    }
+  }
+
+  pub fn view(&self) -> Element<'_, ChooserMessage> {
+    let mut entry_elements: Vec<Element<ChooserMessage>> = Vec::new();
+    for path in &self.entries {
+      let name: String = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+      if path.is_dir() {
+        entry_elements.push(button(text(format!("{}/", name))).width(Length::Fill).style(theme::Button::Secondary).on_press(ChooserMessage::Navigate(path.clone())).into());
+      } else if self.save_name.is_some() {
+        //overwriting a file takes a second click, on the save button
+        entry_elements.push(button(text(name)).width(Length::Fill).style(theme::Button::Secondary).on_press(ChooserMessage::PathInput(path.display().to_string())).into());
+      } else {
+        entry_elements.push(button(text(name)).width(Length::Fill).on_press(ChooserMessage::Choose(path.clone())).into());
+      }
+    }
+    if self.entries.is_empty() {
+      entry_elements.push(text("No folders or matching files here").style(styles::BOOKMARK_TIMESTAMP_STYLE).into());
+    }
+    let typed_path: PathBuf = PathBuf::from(self.path_input.trim());
+    let mut chooser = column![
+      row![
+        button("Up").on_press(ChooserMessage::Up),
+        text_input("Path", &self.path_input).on_input(ChooserMessage::PathInput).on_submit(ChooserMessage::Choose(typed_path.clone())),
+        button(self.choose_label).on_press(ChooserMessage::Choose(typed_path)),
+        checkbox("Show hidden", self.show_hidden, ChooserMessage::ShowHiddenToggle),
+      ].spacing(5).align_items(Alignment::Center),
+    ].spacing(8);
+    if let Some(error) = &self.error {
+      chooser = chooser.push(text(error));
+    }
+    chooser.push(scrollable(Column::with_children(entry_elements).spacing(3))).into()
+  }
+}
@@ -0,0 +1,185 @@
+//typo tolerant matching for the fuzzy search mode. a search word is compared with every word of the text by edit
+//distance (swapping two letters counts as one edit), and with the start of every word so half typed words match too.
+//similarity is 1 minus the edits per letter searched for, so 1 is an exact match
+
+//a run of matched chars in the text, as char indices (start, end)
+pub type Span = (usize, usize);
+
+//edit distance that also counts swapping two neighbouring letters as one edit. gives the distance from a to all of b,
+//and to the start of b that is as long as a (for half typed words), from the same table
+fn edit_distances(a: &[char], b: &[char]) -> (usize, usize) {
+  let prefix_length: usize = a.len().min(b.len());
+  //only the last two rows are needed for the swaps
+  let mut before_previous: Vec<usize> = Vec::new();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  for i in 1..=a.len() {
+    let mut current: Vec<usize> = vec![i; b.len() + 1];
+    for j in 1..=b.len() {
+      let cost: usize = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+      let mut distance: usize = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+      if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+        distance = distance.min(before_previous[j - 2] + 1);
+      }
+      current[j] = distance;
+    }
+    before_previous = std::mem::replace(&mut previous, current);
+  }
+  (previous[b.len()], previous[prefix_length])
+}
+
+//lowercased one char at a time, so indices still line up with the original text
+fn lowercase_chars(text: &str) -> Vec<char> {
+  text.chars().map(|char| char.to_lowercase().next().unwrap_or(char)).collect()
+}
+
+fn words(chars: &[char]) -> Vec<Span> {
+  let mut words: Vec<Span> = Vec::new();
+  let mut start: Option<usize> = None;
+  for (index, char) in chars.iter().enumerate() {
+    match (char.is_alphanumeric(), start) {
+      (true, None) => start = Some(index),
+      (false, Some(word_start)) => {
+        words.push((word_start, index));
+        start = None;
+      },
+      _ => {},
+    }
+  }
+  if let Some(word_start) = start {
+    words.push((word_start, chars.len()));
+  }
+  words
+}
+
+fn find(text: &[char], term: &[char]) -> Option<usize> {
+  if term.is_empty() || term.len() > text.len() {
+    return None;
+  }
+  (0..=text.len() - term.len()).find(|&start| text[start..start + term.len()] == *term)
+}
+
+//the word of the text closest to one search word
+fn best_word(text: &[char], text_words: &[Span], term: &[char]) -> Option<(f64, Span)> {
+  if let Some(start) = find(text, term) {
+    return Some((1.0, (start, start + term.len())));
+  }
+  let mut best: Option<(f64, Span)> = None;
+  for &(start, end) in text_words {
+    let word: &[char] = &text[start..end];
+    let prefix_end: usize = (start + term.len()).min(end);
+    let (whole, prefix) = edit_distances(term, word);
+    let (distance, span) = if prefix < whole { (prefix, (start, prefix_end)) } else { (whole, (start, end)) };
+    let similarity: f64 = 1.0 - distance as f64 / term.len() as f64;
+    if best.map(|(best_similarity, _)| similarity > best_similarity).unwrap_or(true) {
+      best = Some((similarity, span));
+    }
+  }
+  best
+}
+
+//how similar the text is to what was searched for (lowercased), from 0 to 1, and the chars that matched.
+//every word searched for has to be somewhere in the text, the similarity is that of the worst one
+pub fn fuzzy_match(text: &str, term: &str) -> (f64, Vec<Span>) {
+  let text: Vec<char> = lowercase_chars(text);
+  let term: Vec<char> = term.chars().collect();
+  if let Some(start) = find(&text, &term) {
+    return (1.0, vec![(start, start + term.len())]);
+  }
+  let text_words: Vec<Span> = words(&text);
+  let mut similarity: f64 = 1.0;
+  let mut spans: Vec<Span> = Vec::new();
+  for (start, end) in words(&term) {
+    match best_word(&text, &text_words, &term[start..end]) {
+      Some((word_similarity, span)) => {
+        similarity = similarity.min(word_similarity);
+        spans.push(span);
+      },
+      None => return (0.0, Vec::new()),
+    }
+  }
+  if spans.is_empty() {
+    return (0.0, Vec::new());
+  }
+  (similarity.max(0.0), spans)
+}
+
+//the words of a text the same way matching splits them up, for indexing
+pub fn tokens(text: &str) -> Vec<String> {
+  let chars: Vec<char> = lowercase_chars(text);
+  words(&chars).into_iter().map(|(start, end)| chars[start..end].iter().collect()).collect()
+}
+
+pub fn similarity(text: &str, term: &str) -> f64 {
+  fuzzy_match(text, term).0
+}
+
+//the text cut up into (part, highlighted) pieces, for showing which chars matched
+pub fn split_highlighted(text: &str, spans: &[Span]) -> Vec<(String, bool)> {
+  let mut pieces: Vec<(String, bool)> = Vec::new();
+  for (index, char) in text.chars().enumerate() {
+    let highlighted: bool = spans.iter().any(|&(start, end)| index >= start && index < end);
+    match pieces.last_mut() {
+      Some((piece, piece_highlighted)) if *piece_highlighted == highlighted => piece.push(char),
+      _ => pieces.push((char.to_string(), highlighted)),
+    }
+  }
+  pieces
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn exact_matches_are_one() {
+    assert_eq!(fuzzy_match("The Rust Book", "rust"), (1.0, vec![(4, 8)]));
+    //spans count chars, not bytes
+    assert_eq!(fuzzy_match("Über Café", "café"), (1.0, vec![(5, 9)]));
+  }
+
+  #[test]
+  fn swapped_letters_are_one_edit() {
+    let (similarity, spans) = fuzzy_match("rust lang", "rsut");
+    assert_eq!(similarity, 0.75);
+    assert_eq!(spans, vec![(0, 4)]);
+  }
+
+  #[test]
+  fn half_typed_words_match_the_start() {
+    let (similarity, spans) = fuzzy_match("functional programming", "prgo");
+    assert_eq!(similarity, 0.75);
+    assert_eq!(spans, vec![(11, 15)]);
+  }
+
+  #[test]
+  fn the_worst_search_word_counts() {
+    let (similarity, spans) = fuzzy_match("Rust and Haskell", "haskell rsut");
+    assert_eq!(similarity, 0.75);
+    assert_eq!(spans, vec![(9, 16), (0, 4)]);
+  }
+
+  #[test]
+  fn nothing_close_is_zero() {
+    assert_eq!(fuzzy_match("rust", "zzzz").0, 0.0);
+    assert_eq!(fuzzy_match("rust", "!!"), (0.0, Vec::new()));
+    assert_eq!(fuzzy_match("", "rust"), (0.0, Vec::new()));
+  }
+
+  #[test]
+  fn similarity_goes_down_with_edits() {
+    let one: f64 = similarity("bookmarks", "bokmarks");
+    let two: f64 = similarity("bookmarks", "bokmrks");
+    assert!(one > two && two > 0.0 && one < 1.0);
+  }
+
+  #[test]
+  fn tokens_are_lowercased_words() {
+    assert_eq!(tokens("Hello, Wörld-2 https://x.io"), vec!["hello", "wörld", "2", "https", "x", "io"]);
+  }
+
+  #[test]
+  fn highlighting_splits_at_spans() {
+    assert_eq!(split_highlighted("Über Café", &[(5, 9)]), vec![("Über ".to_string(), false), ("Café".to_string(), true)]);
+    assert_eq!(split_highlighted("abc", &[]), vec![("abc".to_string(), false)]);
+  }
+}
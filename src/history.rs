@@ -0,0 +1,198 @@
+use crate::storage::{ Operation, Storage };
+
+//undo and redo for changes to the library. each entry is the list of operations that reverses one user action
+pub struct History {
+  undo_stack: Vec<Vec<Operation>>,
+  redo_stack: Vec<Vec<Operation>>,
+  //the text box the newest undo step was typed into, if it was
+  typing_field: Option<String>,
+}
+
+impl History {
+  //entries hold whole bookmarks (or a whole library, for restored backups), so don't keep them forever
+  const MAX_ENTRIES: usize = 100;
+
+  pub fn new() -> History {
+    History {
+      undo_stack: Vec::new(),
+      redo_stack: Vec::new(),
+      typing_field: None,
+    }
+  }
+
+  //remember the changes the storage made since the last record, as one undo step
+  pub fn record(&mut self, storage: &mut Storage) {
+    let changes: Vec<Operation> = storage.take_changes();
+    if changes.is_empty() {
+      return;
+    }
+    self.push(changes);
+    self.typing_field = None;
+  }
+
+  //like record, but typing on in the same text box stays one undo step. the step's operations already go back to
+  //before the typing started, so the ones for each further keystroke aren't needed
+  pub fn record_typing(&mut self, storage: &mut Storage, field: String) {
+    let changes: Vec<Operation> = storage.take_changes();
+    if changes.is_empty() {
+      return;
+    }
+    if self.typing_field.as_ref() != Some(&field) || self.undo_stack.is_empty() {
+      self.push(changes);
+    }
+    self.typing_field = Some(field);
+  }
+
+  fn push(&mut self, changes: Vec<Operation>) {
+    self.undo_stack.push(changes);
+    if self.undo_stack.len() > History::MAX_ENTRIES {
+      self.undo_stack.remove(0);
+    }
+    //redoing on top of a different change would make no sense
+    self.redo_stack = Vec::new();
+  }
+
+  //apply reversing operations newest first, and return what reverses them in turn
+  fn revert(operations: Vec<Operation>, storage: &mut Storage) -> Vec<Operation> {
+    for operation in operations.into_iter().rev() {
+      storage.apply(operation);
+    }
+    storage.take_changes()
+  }
+
+  //both return whether there was anything to undo/redo (and so something to save)
+  pub fn undo(&mut self, storage: &mut Storage) -> bool {
+    self.typing_field = None;
+    if let Some(operations) = self.undo_stack.pop() {
+      self.redo_stack.push(History::revert(operations, storage));
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn redo(&mut self, storage: &mut Storage) -> bool {
+    self.typing_field = None;
+    if let Some(operations) = self.redo_stack.pop() {
+      self.undo_stack.push(History::revert(operations, storage));
+      true
+    } else {
+      false
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::storage::{ Bookmark, Stored };
+
+  fn bookmark(uuid: &str, title: &str) -> Bookmark {
+    Bookmark {
+      title: title.to_string(),
+      link: format!("https://example.com/{}", uuid),
+      note: None,
+      tags: Vec::new(),
+      uuid: uuid.to_string(),
+      timestamp: 1,
+    }
+  }
+
+  //a library in memory, nothing is saved
+  fn library() -> Storage {
+    let mut storage: Storage = Storage::new();
+    storage.loaded(Stored::empty());
+    storage
+  }
+
+  fn title(storage: &Storage, uuid: &str) -> Option<String> {
+    storage.stored.as_ref().unwrap().bookmarks.get(uuid).map(|bookmark| bookmark.title.clone())
+  }
+
+  #[test]
+  fn typing_in_one_box_is_one_step() {
+    let mut storage: Storage = library();
+    let mut history: History = History::new();
+    storage.add_bookmark(bookmark("a", ""));
+    history.record(&mut storage);
+    for typed in ["R", "Ru", "Rust"] {
+      storage.add_bookmark(bookmark("a", typed));
+      history.record_typing(&mut storage, "title".to_string());
+    }
+    assert_eq!(history.undo_stack.len(), 2);
+    //another box is another step
+    storage.add_bookmark(bookmark("a", "Rust!"));
+    history.record_typing(&mut storage, "note".to_string());
+    assert!(history.undo(&mut storage));
+    assert_eq!(title(&storage, "a").as_deref(), Some("Rust"));
+    assert!(history.undo(&mut storage));
+    assert_eq!(title(&storage, "a").as_deref(), Some(""));
+    assert!(history.undo(&mut storage));
+    assert_eq!(title(&storage, "a"), None);
+    assert!(!history.undo(&mut storage));
+    //and redo brings all the typing back at once
+    assert!(history.redo(&mut storage));
+    assert!(history.redo(&mut storage));
+    assert_eq!(title(&storage, "a").as_deref(), Some("Rust"));
+  }
+
+  #[test]
+  fn typing_again_after_undo_is_a_new_step() {
+    let mut storage: Storage = library();
+    let mut history: History = History::new();
+    storage.add_bookmark(bookmark("a", "R"));
+    history.record_typing(&mut storage, "title".to_string());
+    assert!(history.undo(&mut storage));
+    storage.add_bookmark(bookmark("b", "G"));
+    history.record_typing(&mut storage, "title".to_string());
+    storage.add_bookmark(bookmark("b", "Go"));
+    history.record_typing(&mut storage, "title".to_string());
+    assert_eq!(history.undo_stack.len(), 1);
+  }
+
+  #[test]
+  fn keeps_only_the_newest_entries() {
+    let mut storage: Storage = library();
+    let mut history: History = History::new();
+    for index in 0..History::MAX_ENTRIES + 20 {
+      storage.add_bookmark(bookmark(&index.to_string(), "title"));
+      history.record(&mut storage);
+    }
+    let mut undone: usize = 0;
+    while history.undo(&mut storage) {
+      undone += 1;
+    }
+    assert_eq!(undone, History::MAX_ENTRIES);
+    //the oldest ones can't be undone any more
+    assert_eq!(storage.stored.as_ref().unwrap().bookmarks.len(), 20);
+    assert!(title(&storage, "19").is_some());
+    assert!(title(&storage, "20").is_none());
+  }
+
+  #[test]
+  fn a_new_change_clears_redo() {
+    let mut storage: Storage = library();
+    let mut history: History = History::new();
+    storage.add_bookmark(bookmark("a", "A"));
+    history.record(&mut storage);
+    storage.add_bookmark(bookmark("b", "B"));
+    history.record(&mut storage);
+    assert!(history.undo(&mut storage));
+    assert!(history.redo(&mut storage));
+    assert!(history.undo(&mut storage));
+    storage.add_bookmark(bookmark("c", "C"));
+    history.record(&mut storage);
+    assert!(!history.redo(&mut storage));
+    assert_eq!(title(&storage, "b"), None);
+    assert_eq!(title(&storage, "c").as_deref(), Some("C"));
+  }
+
+  #[test]
+  fn nothing_changed_is_no_step() {
+    let mut storage: Storage = library();
+    let mut history: History = History::new();
+    history.record(&mut storage);
+    history.record_typing(&mut storage, "title".to_string());
+    assert!(!history.undo(&mut storage));
+  }
+}
@@ -0,0 +1,1088 @@
+use std::collections::{ HashMap, HashSet };
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use async_std::task;
+use rusqlite::{ Connection, OpenFlags };
+use serde_json::Value;
+
+use crate::json_storage::JsonStorage;
+use crate::storage::{ Bookmark, Settings, Storage, StorageError, Stored };
+use crate::tags::{ allowed_tags, normalize_tags };
+use crate::utils::{ get_timestamp, link_scheme };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+  //bookmarks.html, which every browser exports
+  Netscape,
+  //a library exported from reservoir, merged in instead of just added
+  Reservoir,
+  //places.sqlite in a firefox profile
+  Firefox,
+  //the Bookmarks file in a chrome/chromium/edge/brave profile
+  Chromium,
+  //read later services' export files
+  Pocket,
+  Pinboard,
+  Raindrop,
+}
+
+impl ImportFormat {
+  pub fn all() -> Vec<ImportFormat> {
+    vec![ImportFormat::Netscape, ImportFormat::Reservoir, ImportFormat::Firefox, ImportFormat::Chromium, ImportFormat::Pocket, ImportFormat::Pinboard, ImportFormat::Raindrop]
+  }
+
+  pub fn extensions(&self) -> Vec<&'static str> {
+    match self {
+      ImportFormat::Netscape => vec!["html", "htm"],
+      ImportFormat::Reservoir => vec!["json"],
+      ImportFormat::Firefox => vec!["sqlite"],
+      ImportFormat::Chromium => vec!["Bookmarks"],
+      ImportFormat::Pocket | ImportFormat::Raindrop => vec!["csv"],
+      ImportFormat::Pinboard => vec!["json"],
+    }
+  }
+}
+
+impl std::fmt::Display for ImportFormat {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      ImportFormat::Netscape => "Browser HTML".to_string(),
+      ImportFormat::Reservoir => "reservoir JSON".to_string(),
+      ImportFormat::Firefox => "Firefox Profile".to_string(),
+      ImportFormat::Chromium => "Chrome Profile".to_string(),
+      ImportFormat::Pocket => "Pocket CSV".to_string(),
+      ImportFormat::Pinboard => "Pinboard JSON".to_string(),
+      ImportFormat::Raindrop => "Raindrop CSV".to_string(),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
+//bookmarks read out of a file, before they are added to the library
+#[derive(Clone, Debug)]
+pub struct Parsed {
+  pub bookmarks: Vec<Bookmark>,
+  //entries that couldn't be turned into a bookmark
+  pub invalid: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportSummary {
+  pub added: usize,
+  //existing bookmarks overwritten by a merge
+  pub updated: usize,
+  pub duplicates: usize,
+  pub invalid: usize,
+  //tags left off for having characters tags can't have
+  pub dropped_tags: usize,
+}
+
+impl std::fmt::Display for ImportSummary {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(formatter, "Added {}", self.added)?;
+    if self.updated > 0 {
+      write!(formatter, ", updated {}", self.updated)?;
+    }
+    write!(formatter, ", skipped {} already in the library, {} invalid", self.duplicates, self.invalid)?;
+    if self.dropped_tags > 0 {
+      write!(formatter, ", left off {} tags with characters tags can't have", self.dropped_tags)?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+  KeepMine,
+  TakeTheirs,
+  //whichever has the later timestamp
+  KeepNewest,
+}
+
+//the same bookmark (by uuid or by link) in the library and in the imported file, but with different contents
+#[derive(Clone, Debug)]
+pub struct Conflict {
+  pub mine: Bookmark,
+  pub theirs: Bookmark,
+  pub resolution: Resolution,
+}
+
+impl Conflict {
+  //what ends up in the library. always keeps the library's uuid, so nothing gets duplicated
+  fn resolved(&self) -> Option<Bookmark> {
+    let take_theirs: bool = match self.resolution {
+      Resolution::KeepMine => false,
+      Resolution::TakeTheirs => true,
+      Resolution::KeepNewest => self.theirs.timestamp > self.mine.timestamp,
+    };
+    if take_theirs {
+      Some(Bookmark {
+        uuid: self.mine.uuid.clone(),
+        ..self.theirs.clone()
+      })
+    } else {
+      None
+    }
+  }
+}
+
+//what an import would add, shown before anything is written
+#[derive(Clone, Debug, Default)]
+pub struct Preview {
+  pub new: Vec<Bookmark>,
+  pub duplicates: usize,
+  pub invalid: usize,
+  pub dropped_tags: usize,
+}
+
+//a reservoir library compared against the current one, waiting for conflicts to be resolved
+#[derive(Clone, Debug, Default)]
+pub struct Merge {
+  pub new: Vec<Bookmark>,
+  pub unchanged: usize,
+  pub conflicts: Vec<Conflict>,
+  pub dropped_tags: usize,
+}
+
+//one tag of a bookmarks.html, with its attribute names lowercased
+struct Tag {
+  name: String,
+  closing: bool,
+  attributes: HashMap<String, String>,
+}
+
+//parses the netscape bookmark format, which is what every browser exports to bookmarks.html.
+//it is not really html (tags are never closed properly), so it is read tag by tag instead of as a tree
+pub struct NetscapeParser<'a> {
+  contents: &'a str,
+  position: usize,
+}
+
+impl<'a> NetscapeParser<'a> {
+  pub fn new(contents: &'a str) -> NetscapeParser<'a> {
+    NetscapeParser {
+      contents,
+      position: 0,
+    }
+  }
+
+  fn decode_entities(text: &str) -> String {
+    let mut decoded: String = String::new();
+    let mut rest: &str = text;
+    while let Some(start) = rest.find('&') {
+      decoded.push_str(&rest[..start]);
+      rest = &rest[start..];
+      let entity_end: Option<usize> = rest.find(';').filter(|end| *end <= 10);
+      let replacement: Option<char> = entity_end.and_then(|end| match &rest[1..end] {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        entity if entity.starts_with("#x") || entity.starts_with("#X") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+        entity if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+        _ => None,
+      });
+      match (replacement, entity_end) {
+        (Some(character), Some(end)) => {
+          decoded.push(character);
+          rest = &rest[end + 1..];
+        },
+        _ => {
+          decoded.push('&');
+          rest = &rest[1..];
+        },
+      }
+    }
+    decoded.push_str(rest);
+    decoded
+  }
+
+  fn parse_attributes(source: &str) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    let mut rest: &str = source.trim_start();
+    while !rest.is_empty() {
+      let name_end: usize = rest.find(|character: char| character == '=' || character.is_whitespace()).unwrap_or(rest.len());
+      let name: String = rest[..name_end].to_lowercase();
+      rest = rest[name_end..].trim_start();
+      let mut value: String = String::new();
+      if let Some(after_equals) = rest.strip_prefix('=') {
+        rest = after_equals.trim_start();
+        let quote: Option<char> = rest.chars().next().filter(|character| *character == '"' || *character == '\'');
+        let value_end: usize;
+        if let Some(quote) = quote {
+          rest = &rest[1..];
+          value_end = rest.find(quote).unwrap_or(rest.len());
+          value = NetscapeParser::decode_entities(&rest[..value_end]);
+          rest = &rest[(value_end + 1).min(rest.len())..];
+        } else {
+          value_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+          value = NetscapeParser::decode_entities(&rest[..value_end]);
+          rest = &rest[value_end..];
+        }
+      }
+      if !name.is_empty() {
+        attributes.insert(name, value);
+      }
+      rest = rest.trim_start();
+    }
+    attributes
+  }
+
+  //the text up to the next tag, and that tag
+  fn next_tag(&mut self) -> Option<(String, Tag)> {
+    let rest: &str = &self.contents[self.position..];
+    let start: usize = rest.find('<')?;
+    let text: String = NetscapeParser::decode_entities(rest[..start].trim());
+    //an unclosed tag at the very end is just cut off
+    let end: usize = start + rest[start..].find('>')?;
+    let source: &str = &rest[start + 1..end];
+    self.position += end + 1;
+    let closing: bool = source.starts_with('/');
+    let source: &str = source.trim_start_matches('/');
+    let name_end: usize = source.find(char::is_whitespace).unwrap_or(source.len());
+    Some((text, Tag {
+      name: source[..name_end].to_lowercase(),
+      closing,
+      attributes: NetscapeParser::parse_attributes(&source[name_end..]),
+    }))
+  }
+
+  //ADD_DATE is in seconds, but some exporters write milliseconds or microseconds
+  fn parse_timestamp(add_date: Option<&String>) -> Option<u64> {
+    let mut timestamp: u64 = add_date?.trim().parse().ok()?;
+    while timestamp > 100_000_000_000 {
+      timestamp /= 1000;
+    }
+    Some(timestamp)
+  }
+
+  pub fn parse(mut self) -> Parsed {
+    let mut bookmarks: Vec<Bookmark> = Vec::new();
+    let mut invalid: usize = 0;
+    //one entry per open <DL>. none for lists that don't add to the path (the toolbar and such)
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut next_folder: Option<String> = None;
+    //the <A> or <H3> that is open, and its attributes
+    let mut open: Option<Tag> = None;
+    //<DD> right after a bookmark is its note (after a folder it describes the folder)
+    let mut last_was_bookmark: bool = false;
+    let mut note_for_last: bool = false;
+    while let Some((text, tag)) = self.next_tag() {
+      if note_for_last && !text.is_empty() {
+        if let Some(bookmark) = bookmarks.last_mut() {
+          bookmark.note = Some(text.clone());
+        }
+      }
+      note_for_last = false;
+      let after_bookmark: bool = last_was_bookmark;
+      last_was_bookmark = false;
+      match (tag.name.as_str(), tag.closing) {
+        ("a", false) | ("h3", false) => {
+          open = Some(tag);
+        },
+        ("h3", true) => {
+          //browsers put a flag on their built in folders, having every bookmark tagged "Bookmarks bar" isn't useful
+          let built_in: bool = open.as_ref().map(|open| open.attributes.contains_key("personal_toolbar_folder") || open.attributes.contains_key("unfiled_bookmarks_folder")).unwrap_or(false);
+          next_folder = if built_in || text.is_empty() { None } else { Some(text.replace('/', "-")) };
+          open = None;
+        },
+        ("a", true) => {
+          let Some(open_tag) = open.take() else {
+            continue;
+          };
+          let link: &str = open_tag.attributes.get("href").map(|href| href.as_str()).unwrap_or_default();
+          let tags: Vec<String> = open_tag.attributes.get("tags").map(|tags| split_tags(tags, ',')).unwrap_or_default();
+          let folder_path: Vec<String> = folders.iter().flatten().cloned().collect();
+          match browser_bookmark(&text, link, tags, &folder_path, NetscapeParser::parse_timestamp(open_tag.attributes.get("add_date"))) {
+            Some(bookmark) => {
+              bookmarks.push(bookmark);
+              last_was_bookmark = true;
+            },
+            None => invalid += 1,
+          }
+        },
+        ("dd", false) => {
+          note_for_last = after_bookmark;
+        },
+        ("dl", false) => {
+          folders.push(next_folder.take());
+        },
+        ("dl", true) => {
+          folders.pop();
+        },
+        _ => {},
+      }
+    }
+    Parsed {
+      bookmarks,
+      invalid,
+    }
+  }
+}
+
+//read and parse a bookmarks.html
+pub async fn read_netscape(path: PathBuf) -> Result<Parsed, StorageError> {
+  task::spawn_blocking(move || {
+    let contents: String = read_to_string(&path).map_err(|error| StorageError::ImportError(error.to_string()))?;
+    if !contents.contains("<DL") && !contents.contains("<dl") {
+      return Err(StorageError::ImportError("this doesn't look like an exported bookmarks.html".to_string()));
+    }
+    Ok(NetscapeParser::new(&contents).parse())
+  }).await
+}
+
+//turn a link and its details into a bookmark, or none if it isn't a link to anything (place: queries, bookmarklets, no scheme).
+//folders are joined into one tag, like with bookmarks.html. tags keep their case here, preview lowercases them if the settings say so
+fn browser_bookmark(title: &str, link: &str, mut tags: Vec<String>, folders: &[String], timestamp: Option<u64>) -> Option<Bookmark> {
+  let link: String = link.trim().to_string();
+  match link_scheme(&link).as_deref() {
+    None | Some("javascript") | Some("place") => return None,
+    Some(_) => {},
+  }
+  if !folders.is_empty() {
+    tags.push(folders.join("/"));
+  }
+  let tags: Vec<String> = normalize_tags(tags, false);
+  let title: String = if title.trim().is_empty() { link.clone() } else { title.trim().to_string() };
+  Some(Bookmark::new(title, link, None, tags, timestamp))
+}
+
+struct FirefoxEntry {
+  kind: i64,
+  place: Option<i64>,
+  parent: i64,
+  title: String,
+  added: Option<i64>,
+  guid: String,
+}
+
+//firefox keeps places.sqlite locked while it runs, so read a copy of it (and its write ahead log, which has the latest changes)
+fn read_firefox_sync(path: PathBuf) -> Result<Parsed, StorageError> {
+  let import_error = |error: &dyn std::fmt::Display| StorageError::ImportError(error.to_string());
+  let copy_path: PathBuf = std::env::temp_dir().join(format!("reservoir_places_{}.sqlite", get_timestamp()));
+  std::fs::copy(&path, &copy_path).map_err(|error| import_error(&error))?;
+  let wal_path: PathBuf = path.with_file_name(format!("{}-wal", path.file_name().unwrap_or_default().to_string_lossy()));
+  let copy_wal_path: PathBuf = copy_path.with_file_name(format!("{}-wal", copy_path.file_name().unwrap_or_default().to_string_lossy()));
+  if wal_path.is_file() {
+    std::fs::copy(&wal_path, &copy_wal_path).map_err(|error| import_error(&error))?;
+  }
+  let result = (|| -> Result<Parsed, rusqlite::Error> {
+    //not read only, sqlite needs to write to replay the log into the copy
+    let connection: Connection = Connection::open_with_flags(&copy_path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let mut places: HashMap<i64, String> = HashMap::new();
+    let mut statement = connection.prepare("SELECT id, url FROM moz_places WHERE id IN (SELECT fk FROM moz_bookmarks)")?;
+    for row in statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))? {
+      let (id, url) = row?;
+      places.insert(id, url);
+    }
+    let mut entries: HashMap<i64, FirefoxEntry> = HashMap::new();
+    let mut statement = connection.prepare("SELECT id, type, fk, parent, title, dateAdded, guid FROM moz_bookmarks ORDER BY position")?;
+    let mut order: Vec<i64> = Vec::new();
+    for row in statement.query_map([], |row| {
+      Ok((row.get::<_, i64>(0)?, FirefoxEntry {
+        kind: row.get(1)?,
+        place: row.get(2)?,
+        parent: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+        title: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        added: row.get(5)?,
+        guid: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+      }))
+    })? {
+      let (id, entry) = row?;
+      order.push(id);
+      entries.insert(id, entry);
+    }
+    Ok(firefox_bookmarks(&entries, &order, &places))
+  })();
+  let _ = std::fs::remove_file(&copy_path);
+  let _ = std::fs::remove_file(&copy_wal_path);
+  let _ = std::fs::remove_file(copy_wal_path.with_extension("sqlite-shm"));
+  result.map_err(|error| import_error(&error))
+}
+
+fn firefox_bookmarks(entries: &HashMap<i64, FirefoxEntry>, order: &[i64], places: &HashMap<i64, String>) -> Parsed {
+  const BOOKMARK: i64 = 1;
+  const FOLDER: i64 = 2;
+  //the built in folders have fixed guids, and don't become tags
+  const ROOTS: [&str; 6] = ["root________", "menu________", "toolbar_____", "unfiled_____", "mobile______", "tags________"];
+  let is_root = |entry: &FirefoxEntry| ROOTS.contains(&entry.guid.as_str());
+  let tags_root: Option<i64> = entries.iter().find(|(_, entry)| entry.guid == "tags________").map(|(id, _)| *id);
+  //tags are folders under the tags root, holding an entry for each place tagged with them
+  let mut place_tags: HashMap<i64, Vec<String>> = HashMap::new();
+  for entry in entries.values() {
+    if let (Some(tag_folder), Some(place)) = (entries.get(&entry.parent), entry.place) {
+      if entry.kind == BOOKMARK && tag_folder.kind == FOLDER && Some(tag_folder.parent) == tags_root {
+        place_tags.entry(place).or_default().push(tag_folder.title.clone());
+      }
+    }
+  }
+  let mut bookmarks: Vec<Bookmark> = Vec::new();
+  let mut invalid: usize = 0;
+  for id in order {
+    let entry: &FirefoxEntry = &entries[id];
+    if entry.kind != BOOKMARK {
+      continue;
+    }
+    let mut folders: Vec<String> = Vec::new();
+    let mut parent: Option<&FirefoxEntry> = entries.get(&entry.parent);
+    let mut in_tags: bool = false;
+    //the depth limit is only there in case of a loop in a broken database
+    while let Some(folder) = parent.filter(|_| folders.len() < 100) {
+      if folder.guid == "tags________" {
+        in_tags = true;
+      }
+      if is_root(folder) {
+        break;
+      }
+      folders.insert(0, folder.title.replace('/', "-"));
+      parent = entries.get(&folder.parent);
+    }
+    if in_tags {
+      continue;
+    }
+    let link: &str = entry.place.and_then(|place| places.get(&place)).map(|url| url.as_str()).unwrap_or_default();
+    let tags: Vec<String> = entry.place.and_then(|place| place_tags.get(&place)).cloned().unwrap_or_default();
+    //dateAdded is in microseconds
+    match browser_bookmark(&entry.title, link, tags, &folders, entry.added.map(|added| added.max(0) as u64 / 1_000_000)) {
+      Some(bookmark) => bookmarks.push(bookmark),
+      None => invalid += 1,
+    }
+  }
+  Parsed {
+    bookmarks,
+    invalid,
+  }
+}
+
+pub async fn read_firefox(path: PathBuf) -> Result<Parsed, StorageError> {
+  task::spawn_blocking(move || read_firefox_sync(path)).await
+}
+
+//chromium timestamps are microseconds since 1601
+fn chromium_timestamp(date_added: &Value) -> Option<u64> {
+  let microseconds: u64 = date_added.as_str()?.parse().ok()?;
+  (microseconds / 1_000_000).checked_sub(11_644_473_600)
+}
+
+fn chromium_node(node: &Value, folders: &mut Vec<String>, parsed: &mut Parsed) {
+  match node["type"].as_str() {
+    Some("url") => {
+      let title: &str = node["name"].as_str().unwrap_or_default();
+      let link: &str = node["url"].as_str().unwrap_or_default();
+      match browser_bookmark(title, link, Vec::new(), folders, chromium_timestamp(&node["date_added"])) {
+        Some(bookmark) => parsed.bookmarks.push(bookmark),
+        None => parsed.invalid += 1,
+      }
+    },
+    Some("folder") => {
+      folders.push(node["name"].as_str().unwrap_or_default().replace('/', "-"));
+      for child in node["children"].as_array().into_iter().flatten() {
+        chromium_node(child, folders, parsed);
+      }
+      folders.pop();
+    },
+    _ => {},
+  }
+}
+
+//the roots (bookmarks bar, other bookmarks, mobile bookmarks) don't become tags, only the folders inside them
+pub async fn read_chromium(path: PathBuf) -> Result<Parsed, StorageError> {
+  task::spawn_blocking(move || {
+    let contents: String = read_to_string(&path).map_err(|error| StorageError::ImportError(error.to_string()))?;
+    let value: Value = serde_json::from_str(&contents).map_err(|error| StorageError::ImportError(error.to_string()))?;
+    let roots = value["roots"].as_object().ok_or_else(|| StorageError::ImportError("this doesn't look like a Bookmarks file".to_string()))?;
+    let mut parsed: Parsed = Parsed {
+      bookmarks: Vec::new(),
+      invalid: 0,
+    };
+    for root in roots.values() {
+      for child in root["children"].as_array().into_iter().flatten() {
+        chromium_node(child, &mut Vec::new(), &mut parsed);
+      }
+    }
+    Ok(parsed)
+  }).await
+}
+
+//the columns of a csv export, by lowercased header name
+fn read_csv(contents: &str, mut to_bookmark: impl FnMut(&HashMap<String, String>) -> Option<Bookmark>) -> Result<Parsed, StorageError> {
+  let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(contents.as_bytes());
+  let headers: Vec<String> = reader.headers().map_err(|error| StorageError::ImportError(error.to_string()))?.iter().map(|header| header.trim().to_lowercase()).collect();
+  let mut parsed: Parsed = Parsed {
+    bookmarks: Vec::new(),
+    invalid: 0,
+  };
+  for record in reader.records() {
+    let Ok(record) = record else {
+      parsed.invalid += 1;
+      continue;
+    };
+    let row: HashMap<String, String> = headers.iter().cloned().zip(record.iter().map(|field| field.to_string())).collect();
+    match to_bookmark(&row) {
+      Some(bookmark) => parsed.bookmarks.push(bookmark),
+      None => parsed.invalid += 1,
+    }
+  }
+  Ok(parsed)
+}
+
+//the services write dates like 2023-01-31T12:00:00.000Z
+fn parse_iso_timestamp(date: &str) -> Option<u64> {
+  chrono::DateTime::parse_from_rfc3339(date.trim()).ok().and_then(|date| u64::try_from(date.timestamp()).ok())
+}
+
+//cleaned up by browser_bookmark
+fn split_tags(tags: &str, separator: char) -> Vec<String> {
+  tags.split(separator).map(|tag| tag.to_string()).collect()
+}
+
+//the note, then the excerpt, whichever of them there are
+fn join_note(parts: &[&str]) -> Option<String> {
+  let parts: Vec<&str> = parts.iter().map(|part| part.trim()).filter(|part| !part.is_empty()).collect();
+  if parts.is_empty() { None } else { Some(parts.join("\n\n")) }
+}
+
+//pocket's export: title, url, time_added (unix seconds), tags (separated by |), status
+fn pocket_bookmark(row: &HashMap<String, String>) -> Option<Bookmark> {
+  let field = |name: &str| row.get(name).map(|value| value.as_str()).unwrap_or_default();
+  browser_bookmark(field("title"), field("url"), split_tags(field("tags"), '|'), &[], field("time_added").trim().parse().ok())
+}
+
+//raindrop's export: id, title, note, excerpt, url, folder, tags (comma separated), created (iso 8601), ...
+fn raindrop_bookmark(row: &HashMap<String, String>) -> Option<Bookmark> {
+  let field = |name: &str| row.get(name).map(|value| value.as_str()).unwrap_or_default();
+  //everything without a collection is in "Unsorted", which isn't worth a tag
+  let folders: Vec<String> = Some(field("folder").trim()).filter(|folder| !folder.is_empty() && *folder != "Unsorted").map(|folder| folder.to_string()).into_iter().collect();
+  let mut bookmark: Bookmark = browser_bookmark(field("title"), field("url"), split_tags(field("tags"), ','), &folders, parse_iso_timestamp(field("created")))?;
+  bookmark.note = join_note(&[field("note"), field("excerpt")]);
+  Some(bookmark)
+}
+
+//pinboard's json export is a list of posts. description is the title, extended is the note, tags are separated by spaces
+fn pinboard_bookmark(post: &Value) -> Option<Bookmark> {
+  let field = |name: &str| post[name].as_str().unwrap_or_default();
+  let mut bookmark: Bookmark = browser_bookmark(field("description"), field("href"), split_tags(field("tags"), ' '), &[], parse_iso_timestamp(field("time")))?;
+  bookmark.note = join_note(&[field("extended")]);
+  Some(bookmark)
+}
+
+fn read_pinboard(contents: &str) -> Result<Parsed, StorageError> {
+  let value: Value = serde_json::from_str(contents).map_err(|error| StorageError::ImportError(error.to_string()))?;
+  let posts: &Vec<Value> = value.as_array().ok_or_else(|| StorageError::ImportError("this doesn't look like a Pinboard export".to_string()))?;
+  let mut parsed: Parsed = Parsed {
+    bookmarks: Vec::new(),
+    invalid: 0,
+  };
+  for post in posts {
+    match pinboard_bookmark(post) {
+      Some(bookmark) => parsed.bookmarks.push(bookmark),
+      None => parsed.invalid += 1,
+    }
+  }
+  Ok(parsed)
+}
+
+//pocket, pinboard and raindrop
+pub async fn read_service_export(path: PathBuf, format: ImportFormat) -> Result<Parsed, StorageError> {
+  task::spawn_blocking(move || {
+    let contents: String = read_to_string(&path).map_err(|error| StorageError::ImportError(error.to_string()))?;
+    match format {
+      ImportFormat::Pocket => read_csv(&contents, pocket_bookmark),
+      ImportFormat::Raindrop => read_csv(&contents, raindrop_bookmark),
+      ImportFormat::Pinboard => read_pinboard(&contents),
+      _ => Err(StorageError::ImportError(format!("{} is not a read later service", format))),
+    }
+  }).await
+}
+
+//sort out what would be added, without touching the library. duplicates are links already in the library, or
+//earlier in the same file
+pub fn preview(storage: &Storage, parsed: Parsed) -> Preview {
+  let mut preview: Preview = Preview {
+    invalid: parsed.invalid,
+    ..Preview::default()
+  };
+  let settings: &Settings = &storage.stored.as_ref().unwrap().settings;
+  let mut seen: HashSet<String> = storage.canonical_links();
+  for mut bookmark in parsed.bookmarks {
+    let (tags, dropped): (Vec<String>, usize) = allowed_tags(bookmark.tags, settings);
+    bookmark.tags = tags;
+    preview.dropped_tags += dropped;
+    if !seen.insert(storage.canonical_link(&bookmark.link)) {
+      preview.duplicates += 1;
+    } else {
+      preview.new.push(bookmark);
+    }
+  }
+  preview
+}
+
+pub fn add_preview(storage: &mut Storage, preview: Preview) -> ImportSummary {
+  let mut summary: ImportSummary = ImportSummary {
+    duplicates: preview.duplicates,
+    invalid: preview.invalid,
+    dropped_tags: preview.dropped_tags,
+    ..ImportSummary::default()
+  };
+  //the library could have changed while the preview was up
+  add_new(storage, preview.new, &mut summary);
+  summary
+}
+
+//add the bookmarks whose links aren't in the library yet
+fn add_new(storage: &mut Storage, bookmarks: Vec<Bookmark>, summary: &mut ImportSummary) {
+  let mut links: HashSet<String> = storage.canonical_links();
+  for bookmark in bookmarks {
+    if links.insert(storage.canonical_link(&bookmark.link)) {
+      storage.add_bookmark(bookmark);
+      summary.added += 1;
+    } else {
+      summary.duplicates += 1;
+    }
+  }
+}
+
+//read a library exported from reservoir, migrating it if it came from an older version
+pub async fn read_reservoir(path: PathBuf) -> Result<Stored, StorageError> {
+  task::spawn_blocking(move || {
+    let contents: String = read_to_string(&path).map_err(|error| StorageError::ImportError(error.to_string()))?;
+    Ok(JsonStorage::parse(&contents).map_err(|error| StorageError::ImportError(error.to_string()))?.0)
+  }).await
+}
+
+//match up the imported bookmarks with the library, by uuid first and then by link
+pub fn merge(storage: &Storage, theirs: Stored) -> Merge {
+  let mine: &Stored = storage.stored.as_ref().unwrap();
+  let by_link: HashMap<String, &Bookmark> = mine.bookmarks.values().map(|bookmark| (storage.canonical_link(&bookmark.link), bookmark)).collect();
+  let mut merge: Merge = Merge::default();
+  let mut theirs: Vec<Bookmark> = theirs.bookmarks.into_values().collect();
+  theirs.sort_unstable_by_key(|bookmark| bookmark.timestamp);
+  for mut bookmark in theirs {
+    //the other library can have its own settings, its tags follow this one's
+    let (tags, dropped): (Vec<String>, usize) = allowed_tags(bookmark.tags, &mine.settings);
+    bookmark.tags = tags;
+    merge.dropped_tags += dropped;
+    let matching: Option<&Bookmark> = mine.bookmarks.get(&bookmark.uuid).or_else(|| by_link.get(&storage.canonical_link(&bookmark.link)).copied());
+    match matching {
+      None => merge.new.push(bookmark),
+      Some(mine_bookmark) => {
+        let same: bool = mine_bookmark.title == bookmark.title && mine_bookmark.link == bookmark.link && mine_bookmark.tags == bookmark.tags && mine_bookmark.note == bookmark.note && mine_bookmark.timestamp == bookmark.timestamp;
+        if same {
+          merge.unchanged += 1;
+        } else {
+          merge.conflicts.push(Conflict {
+            mine: mine_bookmark.clone(),
+            theirs: bookmark,
+            resolution: Resolution::KeepNewest,
+          });
+        }
+      },
+    }
+  }
+  merge
+}
+
+pub fn apply_merge(storage: &mut Storage, merge: Merge) -> ImportSummary {
+  let mut summary: ImportSummary = ImportSummary {
+    duplicates: merge.unchanged,
+    dropped_tags: merge.dropped_tags,
+    ..ImportSummary::default()
+  };
+  for conflict in &merge.conflicts {
+    match conflict.resolved() {
+      Some(bookmark) => {
+        storage.add_bookmark(bookmark);
+        summary.updated += 1;
+      },
+      None => summary.duplicates += 1,
+    }
+  }
+  //two bookmarks in the file could still share a link
+  add_new(storage, merge.new, &mut summary);
+  summary
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BOOKMARKS_HTML: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+  <DT><H3 ADD_DATE="1" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+  <DL><p>
+    <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1600000000" TAGS="lang, Rust,, rust">Rust &amp; Cargo</A>
+    <DD>The language &#x26; its tools
+    <DT><H3>Dev / Tools</H3>
+    <DD>About the folder
+    <DL><p>
+      <DT><A HREF="https://github.com" ADD_DATE="1600000000000">GitHub</A>
+      <DT><H3>Nested</H3>
+      <DL><p>
+        <DT><a href='https://docs.rs' add_date=1600000000000000>   </a>
+      </DL><p>
+    </DL><p>
+    <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    <DT><A HREF="place:sort=8">Recent</A>
+    <DT><A HREF="mailto:me@example.com">Mail me</A>
+    <DT><A HREF="notes.html">No scheme</A>
+  </DL><p>
+  <DT><A HREF="https://example.com">Top level</A>
+</DL><p>
+"#;
+
+  fn by_title<'a>(parsed: &'a Parsed, title: &str) -> &'a Bookmark {
+    parsed.bookmarks.iter().find(|bookmark| bookmark.title == title).unwrap()
+  }
+
+  #[test]
+  fn reads_bookmarks_with_their_details() {
+    let parsed: Parsed = NetscapeParser::new(BOOKMARKS_HTML).parse();
+    assert_eq!(parsed.bookmarks.len(), 5);
+    let rust: &Bookmark = by_title(&parsed, "Rust & Cargo");
+    assert_eq!(rust.link, "https://www.rust-lang.org/");
+    assert_eq!(rust.timestamp, 1600000000);
+    assert_eq!(rust.note.as_deref(), Some("The language & its tools"));
+    //the toolbar is built in, so it isn't a tag
+    assert_eq!(rust.tags, vec!["lang", "Rust"]);
+  }
+
+  #[test]
+  fn folders_become_one_tag() {
+    let parsed: Parsed = NetscapeParser::new(BOOKMARKS_HTML).parse();
+    let github: &Bookmark = by_title(&parsed, "GitHub");
+    assert_eq!(github.tags, vec!["Dev - Tools"]);
+    //a <DD> after a folder describes the folder, not the bookmark before it
+    assert_eq!(github.note, None);
+    let docs: &Bookmark = by_title(&parsed, "https://docs.rs");
+    assert_eq!(docs.tags, vec!["Dev - Tools/Nested"]);
+    assert!(by_title(&parsed, "Top level").tags.is_empty());
+  }
+
+  #[test]
+  fn timestamps_are_brought_to_seconds() {
+    let parsed: Parsed = NetscapeParser::new(BOOKMARKS_HTML).parse();
+    assert_eq!(by_title(&parsed, "GitHub").timestamp, 1600000000);
+    assert_eq!(by_title(&parsed, "https://docs.rs").timestamp, 1600000000);
+  }
+
+  #[test]
+  fn counts_links_that_go_nowhere() {
+    let parsed: Parsed = NetscapeParser::new(BOOKMARKS_HTML).parse();
+    assert_eq!(parsed.invalid, 3);
+    //links that aren't web pages are still links
+    assert_eq!(by_title(&parsed, "Mail me").link, "mailto:me@example.com");
+  }
+
+  #[test]
+  fn survives_a_cut_off_file() {
+    let cut: &str = &BOOKMARKS_HTML[..BOOKMARKS_HTML.find("GitHub").unwrap() - 20];
+    let parsed: Parsed = NetscapeParser::new(cut).parse();
+    assert_eq!(parsed.bookmarks.len(), 1);
+  }
+
+  fn firefox_entry(kind: i64, place: Option<i64>, parent: i64, title: &str, guid: &str) -> FirefoxEntry {
+    FirefoxEntry {
+      kind,
+      place,
+      parent,
+      title: title.to_string(),
+      added: Some(1_600_000_000_000_000),
+      guid: guid.to_string(),
+    }
+  }
+
+  //a toolbar with a folder in it, the bookmarks menu, and tags kept the way firefox does (a folder per tag under the tags root)
+  fn firefox_library() -> (HashMap<i64, FirefoxEntry>, Vec<i64>, HashMap<i64, String>) {
+    let entries: Vec<(i64, FirefoxEntry)> = vec![
+      (1, firefox_entry(2, None, 0, "", "root________")),
+      (2, firefox_entry(2, None, 1, "menu", "menu________")),
+      (3, firefox_entry(2, None, 1, "toolbar", "toolbar_____")),
+      (4, firefox_entry(2, None, 1, "tags", "tags________")),
+      (10, firefox_entry(2, None, 3, "Dev/Tools", "folder000001")),
+      (11, firefox_entry(1, Some(100), 10, "Rust", "bookmark0001")),
+      (12, firefox_entry(1, Some(101), 2, "", "bookmark0002")),
+      (13, firefox_entry(1, Some(102), 2, "Recently Bookmarked", "bookmark0003")),
+      (14, firefox_entry(1, Some(999), 10, "Missing", "bookmark0004")),
+      (20, firefox_entry(2, None, 4, "lang", "tag000000001")),
+      (21, firefox_entry(1, Some(100), 20, "", "tagentry0001")),
+      (22, firefox_entry(2, None, 4, "Rust", "tag000000002")),
+      (23, firefox_entry(1, Some(100), 22, "", "tagentry0002")),
+    ];
+    let order: Vec<i64> = entries.iter().map(|(id, _)| *id).collect();
+    let places: HashMap<i64, String> = HashMap::from([
+      (100, "https://www.rust-lang.org".to_string()),
+      (101, "https://example.com".to_string()),
+      (102, "place:sort=8".to_string()),
+    ]);
+    (entries.into_iter().collect(), order, places)
+  }
+
+  #[test]
+  fn reads_firefox_bookmarks_with_folders_and_tags() {
+    let (entries, order, places) = firefox_library();
+    let parsed: Parsed = firefox_bookmarks(&entries, &order, &places);
+    assert_eq!(parsed.bookmarks.len(), 2);
+    let rust: &Bookmark = &parsed.bookmarks[0];
+    assert_eq!(rust.link, "https://www.rust-lang.org");
+    assert_eq!(rust.timestamp, 1_600_000_000);
+    let mut tags: Vec<String> = rust.tags.clone();
+    tags.sort();
+    //the toolbar is built in, the folder inside it is a tag
+    assert_eq!(tags, vec!["Dev-Tools", "Rust", "lang"]);
+    let untitled: &Bookmark = &parsed.bookmarks[1];
+    assert_eq!(untitled.title, "https://example.com");
+    assert!(untitled.tags.is_empty());
+  }
+
+  #[test]
+  fn firefox_tag_entries_and_queries_are_not_bookmarks() {
+    let (entries, order, places) = firefox_library();
+    let parsed: Parsed = firefox_bookmarks(&entries, &order, &places);
+    assert!(parsed.bookmarks.iter().all(|bookmark| bookmark.link != "place:sort=8"));
+    //the query and the bookmark whose place is gone. the entries under the tags root aren't counted at all
+    assert_eq!(parsed.invalid, 2);
+  }
+
+  #[test]
+  fn reads_chromium_folders_as_tags() {
+    let node: Value = serde_json::json!({
+      "type": "folder",
+      "name": "Dev/Tools",
+      "children": [
+        { "type": "url", "name": "Rust", "url": "https://www.rust-lang.org", "date_added": "13244473600000000" },
+        { "type": "folder", "name": "Docs", "children": [
+          { "type": "url", "name": "", "url": "https://docs.rs" },
+        ] },
+        { "type": "url", "name": "Bookmarklet", "url": "javascript:alert(1)" },
+        { "type": "separator" },
+      ],
+    });
+    let mut folders: Vec<String> = Vec::new();
+    let mut parsed: Parsed = Parsed {
+      bookmarks: Vec::new(),
+      invalid: 0,
+    };
+    chromium_node(&node, &mut folders, &mut parsed);
+    assert!(folders.is_empty());
+    assert_eq!(parsed.invalid, 1);
+    assert_eq!(parsed.bookmarks.len(), 2);
+    assert_eq!(parsed.bookmarks[0].title, "Rust");
+    assert_eq!(parsed.bookmarks[0].tags, vec!["Dev-Tools"]);
+    assert_eq!(parsed.bookmarks[0].timestamp, 1_600_000_000);
+    assert_eq!(parsed.bookmarks[1].title, "https://docs.rs");
+    assert_eq!(parsed.bookmarks[1].tags, vec!["Dev-Tools/Docs"]);
+  }
+
+  #[test]
+  fn chromium_timestamps_are_strings_counting_from_1601() {
+    assert_eq!(chromium_timestamp(&Value::from("13244473600000000")), Some(1_600_000_000));
+    assert_eq!(chromium_timestamp(&Value::from("1000")), None);
+    assert_eq!(chromium_timestamp(&Value::from(13244473600000000u64)), None);
+  }
+
+  #[test]
+  fn reads_a_pocket_export() {
+    let contents: &str = "title,url,time_added,tags,status
+\"Rust, the language\",https://www.rust-lang.org,1600000000,lang|Rust|rust,unread
+,https://docs.rs,yesterday,,archive
+Bookmarklet,javascript:alert(1),1600000000,,unread
+Cut off
+";
+    let parsed: Parsed = read_csv(contents, pocket_bookmark).unwrap();
+    assert_eq!(parsed.invalid, 2);
+    assert_eq!(parsed.bookmarks.len(), 2);
+    assert_eq!(parsed.bookmarks[0].title, "Rust, the language");
+    assert_eq!(parsed.bookmarks[0].link, "https://www.rust-lang.org");
+    assert_eq!(parsed.bookmarks[0].tags, vec!["lang", "Rust"]);
+    assert_eq!(parsed.bookmarks[0].timestamp, 1_600_000_000);
+    //no title is the link, and a date that doesn't parse is now
+    assert_eq!(parsed.bookmarks[1].title, "https://docs.rs");
+    assert!(parsed.bookmarks[1].tags.is_empty());
+    assert!(parsed.bookmarks[1].timestamp > 1_600_000_000);
+  }
+
+  #[test]
+  fn reads_a_raindrop_export() {
+    let contents: &str = "id,title,note,excerpt,url,folder,tags,created
+1,The book,\"Read it, twice\",\"The \"\"Rust\"\"
+book\",https://doc.rust-lang.org/book,Dev,\"rust, lang\",2020-09-13T12:26:40.000Z
+2,Example,,,https://example.com,Unsorted,,2020-09-13
+3,No link,,,,Dev,,
+";
+    let parsed: Parsed = read_csv(contents, raindrop_bookmark).unwrap();
+    assert_eq!(parsed.invalid, 1);
+    assert_eq!(parsed.bookmarks.len(), 2);
+    let book: &Bookmark = &parsed.bookmarks[0];
+    assert_eq!(book.link, "https://doc.rust-lang.org/book");
+    assert_eq!(book.note.as_deref(), Some("Read it, twice\n\nThe \"Rust\"\nbook"));
+    //the collection is one more tag
+    assert_eq!(book.tags, vec!["rust", "lang", "Dev"]);
+    assert_eq!(book.timestamp, 1_600_000_000);
+    let example: &Bookmark = &parsed.bookmarks[1];
+    assert!(example.tags.is_empty());
+    assert_eq!(example.note, None);
+    assert!(example.timestamp > 1_600_000_000);
+  }
+
+  #[test]
+  fn reads_a_pinboard_export() {
+    let contents: &str = r#"[
+      { "href": "https://www.rust-lang.org", "description": "Rust", "extended": "The language", "time": "2020-09-13T12:26:40Z", "tags": "lang  rust Lang" },
+      { "href": "mailto:me@example.com", "description": "", "extended": "", "time": "2020-09-13T12:26:40Z", "tags": "" },
+      { "href": "place:sort=8", "description": "Recent" },
+      { "description": "No link" }
+    ]"#;
+    let parsed: Parsed = read_pinboard(contents).unwrap();
+    assert_eq!(parsed.invalid, 2);
+    assert_eq!(parsed.bookmarks.len(), 2);
+    assert_eq!(parsed.bookmarks[0].title, "Rust");
+    assert_eq!(parsed.bookmarks[0].note.as_deref(), Some("The language"));
+    assert_eq!(parsed.bookmarks[0].tags, vec!["lang", "rust"]);
+    assert_eq!(parsed.bookmarks[0].timestamp, 1_600_000_000);
+    assert_eq!(parsed.bookmarks[1].title, "mailto:me@example.com");
+    assert_eq!(parsed.bookmarks[1].note, None);
+    assert!(read_pinboard("{}").is_err());
+  }
+
+  //a library in memory, nothing is saved
+  fn library(bookmarks: &[Bookmark], settings: Settings) -> Storage {
+    let mut storage: Storage = Storage::new();
+    storage.loaded(Stored {
+      bookmarks: bookmarks.iter().map(|bookmark| (bookmark.uuid.clone(), bookmark.clone())).collect(),
+      settings,
+      ..Stored::empty()
+    });
+    storage
+  }
+
+  #[test]
+  fn preview_leaves_off_tags_with_other_characters() {
+    let mut storage: Storage = library(&[], Settings::default());
+    let parsed: Parsed = read_pinboard(r#"[
+      { "href": "https://www.rust-lang.org", "description": "Rust", "tags": "lang a,b rust? Rust" },
+      { "href": "https://docs.rs", "description": "Docs", "tags": "docs:rust" }
+    ]"#).unwrap();
+    let preview: Preview = preview(&storage, parsed);
+    assert_eq!(preview.new[0].tags, vec!["lang", "Rust"]);
+    assert!(preview.new[1].tags.is_empty());
+    assert_eq!(preview.dropped_tags, 3);
+    let summary: ImportSummary = add_preview(&mut storage, preview);
+    assert_eq!(summary.dropped_tags, 3);
+    assert!(summary.to_string().ends_with("left off 3 tags with characters tags can't have"));
+  }
+
+  fn merge_bookmark(uuid: &str, link: &str, title: &str, tags: &[&str], timestamp: u64) -> Bookmark {
+    Bookmark {
+      title: title.to_string(),
+      link: format!("https://example.com/{}", link),
+      note: None,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      uuid: uuid.to_string(),
+      timestamp,
+    }
+  }
+
+  //mine has a, b, c and d. theirs changed a (newer, same uuid) and b (older, other uuid but the same link),
+  //has c as it is, and adds e twice under different uuids
+  fn merge_libraries() -> (Storage, Stored) {
+    let mine: Storage = library(&[
+      merge_bookmark("a", "a", "Mine a", &["one"], 10),
+      merge_bookmark("b", "b", "Mine b", &[], 10),
+      merge_bookmark("c", "c", "Same c", &[], 10),
+      merge_bookmark("d", "d", "Only mine", &[], 10),
+    ], Settings {
+      lowercase_tags: true,
+      ..Settings::default()
+    });
+    let theirs: Vec<Bookmark> = vec![
+      merge_bookmark("a", "a", "Theirs a", &["One", "two"], 20),
+      merge_bookmark("their b", "b", "Theirs b", &[], 5),
+      merge_bookmark("c", "c", "Same c", &[], 10),
+      merge_bookmark("e", "e", "New e", &["Rust", " rust", "lang / rust"], 30),
+      merge_bookmark("e again", "e", "New e again", &[], 40),
+    ];
+    let theirs: Stored = Stored {
+      bookmarks: theirs.into_iter().map(|bookmark| (bookmark.uuid.clone(), bookmark)).collect(),
+      ..Stored::empty()
+    };
+    (mine, theirs)
+  }
+
+  #[test]
+  fn merge_sorts_out_new_same_and_different() {
+    let (mine, theirs) = merge_libraries();
+    let merge: Merge = merge(&mine, theirs);
+    assert_eq!(merge.unchanged, 1);
+    assert_eq!(merge.new.iter().map(|bookmark| bookmark.uuid.as_str()).collect::<Vec<&str>>(), vec!["e", "e again"]);
+    //their tags follow this library's settings
+    assert_eq!(merge.new[0].tags, vec!["rust", "lang/rust"]);
+    let mut conflicts: Vec<(&str, &str)> = merge.conflicts.iter().map(|conflict| (conflict.mine.uuid.as_str(), conflict.theirs.uuid.as_str())).collect();
+    conflicts.sort();
+    assert_eq!(conflicts, vec![("a", "a"), ("b", "their b")]);
+    assert!(merge.conflicts.iter().all(|conflict| conflict.resolution == Resolution::KeepNewest));
+    assert_eq!(merge.conflicts.iter().find(|conflict| conflict.mine.uuid == "a").unwrap().theirs.tags, vec!["one", "two"]);
+  }
+
+  #[test]
+  fn resolving_keeps_the_library_uuid() {
+    let conflict: Conflict = Conflict {
+      mine: merge_bookmark("mine", "b", "Mine", &[], 10),
+      theirs: merge_bookmark("theirs", "b", "Theirs", &["tag"], 5),
+      resolution: Resolution::TakeTheirs,
+    };
+    assert_eq!(conflict.resolved(), Some(merge_bookmark("mine", "b", "Theirs", &["tag"], 5)));
+    assert_eq!(Conflict { resolution: Resolution::KeepMine, ..conflict.clone() }.resolved(), None);
+    //theirs is older
+    assert_eq!(Conflict { resolution: Resolution::KeepNewest, ..conflict.clone() }.resolved(), None);
+    //a tie keeps mine
+    let tie: Conflict = Conflict {
+      theirs: merge_bookmark("theirs", "b", "Theirs", &[], 10),
+      resolution: Resolution::KeepNewest,
+      ..conflict
+    };
+    assert_eq!(tie.resolved(), None);
+  }
+
+  #[test]
+  fn applying_a_merge_follows_the_resolution() {
+    //titles of a and b afterwards, and how many were updated
+    let cases: [(Resolution, &str, &str, usize); 3] = [
+      (Resolution::KeepMine, "Mine a", "Mine b", 0),
+      (Resolution::TakeTheirs, "Theirs a", "Theirs b", 2),
+      (Resolution::KeepNewest, "Theirs a", "Mine b", 1),
+    ];
+    for (resolution, a_title, b_title, updated) in cases {
+      let (mut mine, theirs) = merge_libraries();
+      let mut merge: Merge = merge(&mine, theirs);
+      for conflict in merge.conflicts.iter_mut() {
+        conflict.resolution = resolution;
+      }
+      let summary: ImportSummary = apply_merge(&mut mine, merge);
+      assert_eq!(summary.updated, updated, "{:?}", resolution);
+      assert_eq!(summary.added, 1, "{:?}", resolution);
+      //c, the conflicts that were kept, and the second e
+      assert_eq!(summary.duplicates, 1 + (2 - updated) + 1, "{:?}", resolution);
+      let stored: &Stored = mine.stored.as_ref().unwrap();
+      let mut uuids: Vec<&str> = stored.bookmarks.keys().map(String::as_str).collect();
+      uuids.sort();
+      assert_eq!(uuids, vec!["a", "b", "c", "d", "e"], "{:?}", resolution);
+      assert_eq!(stored.bookmarks["a"].title, a_title, "{:?}", resolution);
+      assert_eq!(stored.bookmarks["b"].title, b_title, "{:?}", resolution);
+      let a_tags: Vec<&str> = if a_title == "Mine a" { vec!["one"] } else { vec!["one", "two"] };
+      assert_eq!(stored.bookmarks["a"].tags, a_tags, "{:?}", resolution);
+      assert_eq!(stored.bookmarks["e"].tags, vec!["rust", "lang/rust"], "{:?}", resolution);
+    }
+  }
+
+  #[test]
+  fn decodes_entities() {
+    assert_eq!(NetscapeParser::decode_entities("&#x41;&#66;&lt;&gt;&quot;&apos;"), "AB<>\"'");
+    assert_eq!(NetscapeParser::decode_entities("a & b &unknown; &amp"), "a & b &unknown; &amp");
+  }
+}
@@ -0,0 +1,249 @@
+use std::path::PathBuf;
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, pick_list, text, scrollable, row, column, Column };
+
+use crate::file_chooser::{ ChooserMessage, FileChooser };
+use crate::import::{ add_preview, apply_merge, merge, preview, Conflict, ImportFormat, Merge, Parsed, Preview, Resolution };
+use crate::storage::{ Bookmark, Storage, StorageError, Stored };
+use crate::utils::timestamp_to_string;
+use crate::styles;
+
+#[derive(Clone, Debug)]
+pub enum ImportMessage {
+  FormatChange(ImportFormat),
+  Chooser(ChooserMessage),
+  //bookmarks were read, and are shown before being added
+  Parsed(Result<Parsed, StorageError>),
+  ConfirmImport,
+  //a reservoir library was read, and needs merging
+  Read(Result<Box<Stored>, StorageError>),
+  Resolve(usize, Resolution),
+  ResolveAll(Resolution),
+  FinishMerge,
+  Close,
+}
+
+impl ImportMessage {
+  pub fn is_save_after(message: &ImportMessage) -> bool {
+    matches!(message, ImportMessage::ConfirmImport | ImportMessage::FinishMerge)
+  }
+}
+
+pub struct ImportView {
+  pub format: ImportFormat,
+  file_chooser: FileChooser,
+  importing: bool,
+  //an import waiting to be confirmed
+  preview: Option<Preview>,
+  //a merge waiting for its conflicts to be looked at
+  merge: Option<Merge>,
+  //the summary of the last import, or what went wrong
+  status: Option<String>,
+}
+
+impl ImportView {
+  //big imports are only partly listed in the preview
+  const MAX_PREVIEW: usize = 500;
+
+  pub fn new() -> ImportView {
+    ImportView {
+      format: ImportFormat::Netscape,
+      file_chooser: FileChooser::new(ImportFormat::Netscape.extensions(), "Import"),
+      importing: false,
+      preview: None,
+      merge: None,
+      status: None,
+    }
+  }
+
+  pub fn update(&mut self, message: ImportMessage, storage: &mut Storage) {
+    match message {
+      ImportMessage::FormatChange(format) => {
+        self.format = format;
+        self.file_chooser.set_extensions(format.extensions());
+      },
+      ImportMessage::Chooser(chooser_message) => {
+        if self.file_chooser.chosen(&chooser_message).is_some() {
+          self.importing = true;
+          self.status = Some("Reading bookmarks...".to_string());
+        }
+        self.file_chooser.update(chooser_message);
+      },
+      ImportMessage::Parsed(result) => {
+        self.importing = false;
+        match result {
+          Ok(parsed) => {
+            let preview: Preview = preview(storage, parsed);
+            let mut status: String = format!("Would add {}, skipping {} already in the library and {} invalid", preview.new.len(), preview.duplicates, preview.invalid);
+            if preview.dropped_tags > 0 {
+              status.push_str(&format!(", leaving off {} tags with characters tags can't have", preview.dropped_tags));
+            }
+            self.status = Some(format!("{}. Nothing has been imported yet", status));
+            self.preview = Some(preview);
+          },
+          Err(error) => {
+            self.status = Some(error.to_string());
+          },
+        }
+      },
+      ImportMessage::ConfirmImport => {
+        if let Some(preview) = self.preview.take() {
+          self.status = Some(add_preview(storage, preview).to_string());
+        }
+      },
+      ImportMessage::Read(result) => {
+        self.importing = false;
+        match result {
+          Ok(theirs) => {
+            let merge: Merge = merge(storage, *theirs);
+            let mut status: String = format!("{} new, {} already the same, {} different from the library", merge.new.len(), merge.unchanged, merge.conflicts.len());
+            if merge.dropped_tags > 0 {
+              status.push_str(&format!(", {} tags with characters tags can't have will be left off", merge.dropped_tags));
+            }
+            self.status = Some(status);
+            self.merge = Some(merge);
+          },
+          Err(error) => {
+            self.status = Some(error.to_string());
+          },
+        }
+      },
+      ImportMessage::Resolve(index, resolution) => {
+        if let Some(conflict) = self.merge.as_mut().and_then(|merge| merge.conflicts.get_mut(index)) {
+          conflict.resolution = resolution;
+        }
+      },
+      ImportMessage::ResolveAll(resolution) => {
+        for conflict in self.merge.iter_mut().flat_map(|merge| merge.conflicts.iter_mut()) {
+          conflict.resolution = resolution;
+        }
+      },
+      ImportMessage::FinishMerge => {
+        if let Some(merge) = self.merge.take() {
+          self.status = Some(apply_merge(storage, merge).to_string());
+        }
+      },
+      ImportMessage::Close => {
+        self.preview = None;
+        self.merge = None;
+        self.status = None;
+      },
+    }
+  }
+
+  //the file to read, if this message picked one
+  pub fn chosen(&self, message: &ImportMessage) -> Option<PathBuf> {
+    match message {
+      ImportMessage::Chooser(chooser_message) => self.file_chooser.chosen(chooser_message),
+      _ => None,
+    }
+  }
+
+  fn bookmark_column<'a>(heading: &str, bookmark: &'a Bookmark) -> Element<'a, ImportMessage> {
+    let mut bookmark_column = column![
+      text(heading).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      text(&bookmark.title),
+      text(&bookmark.link),
+      text(timestamp_to_string(bookmark.timestamp)).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+    ].spacing(2).width(Length::FillPortion(2));
+    if !bookmark.tags.is_empty() {
+      bookmark_column = bookmark_column.push(text(format!("Tags: {}", bookmark.tags.join(", "))));
+    }
+    if let Some(note) = &bookmark.note {
+      bookmark_column = bookmark_column.push(text(note));
+    }
+    bookmark_column.into()
+  }
+
+  fn conflict_view(index: usize, conflict: &Conflict) -> Element<'_, ImportMessage> {
+    let mut choices: Vec<Element<ImportMessage>> = Vec::new();
+    for (label, resolution) in [("Keep Mine", Resolution::KeepMine), ("Take Theirs", Resolution::TakeTheirs), ("Keep Newest", Resolution::KeepNewest)] {
+      choices.push(
+        button(text(label)).width(Length::Fill).style(if conflict.resolution == resolution { theme::Button::Primary } else { theme::Button::Secondary }).on_press(ImportMessage::Resolve(index, resolution)).into()
+      );
+    }
+    container(
+      row![
+        ImportView::bookmark_column("Mine", &conflict.mine),
+        ImportView::bookmark_column("Theirs", &conflict.theirs),
+        Column::with_children(choices).spacing(3).width(Length::FillPortion(1)),
+      ].spacing(10)
+    ).style(theme::Container::Custom(Box::new(styles::BookmarkContainer))).padding(10).into()
+  }
+
+  fn preview_view(preview: &Preview) -> Element<'_, ImportMessage> {
+    let mut preview_column = column![
+      row![
+        text(format!("{} new bookmarks", preview.new.len())).width(Length::Fill),
+        button(text(format!("Import {} Bookmarks", preview.new.len()))).on_press(ImportMessage::ConfirmImport).style(theme::Button::Positive),
+      ].spacing(5).align_items(Alignment::Center),
+    ].spacing(5);
+    for bookmark in preview.new.iter().take(ImportView::MAX_PREVIEW) {
+      let mut details: String = format!("{}  {}", timestamp_to_string(bookmark.timestamp), bookmark.link);
+      if !bookmark.tags.is_empty() {
+        details.push_str(&format!("  [{}]", bookmark.tags.join(", ")));
+      }
+      preview_column = preview_column.push(column![
+        text(&bookmark.title),
+        text(details).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      ]);
+    }
+    if preview.new.len() > ImportView::MAX_PREVIEW {
+      preview_column = preview_column.push(text(format!("...and {} more", preview.new.len() - ImportView::MAX_PREVIEW)).style(styles::BOOKMARK_TIMESTAMP_STYLE));
+    }
+    scrollable(preview_column).into()
+  }
+
+  fn merge_view(merge: &Merge) -> Element<'_, ImportMessage> {
+    let mut merge_column = column![
+      row![
+        text("For every conflict:").width(Length::Fill),
+        button("Keep Mine").on_press(ImportMessage::ResolveAll(Resolution::KeepMine)),
+        button("Take Theirs").on_press(ImportMessage::ResolveAll(Resolution::TakeTheirs)),
+        button("Keep Newest").on_press(ImportMessage::ResolveAll(Resolution::KeepNewest)),
+        button("Finish Import").on_press(ImportMessage::FinishMerge).style(theme::Button::Positive),
+      ].spacing(5).align_items(Alignment::Center),
+    ].spacing(10);
+    for (index, conflict) in merge.conflicts.iter().enumerate() {
+      merge_column = merge_column.push(ImportView::conflict_view(index, conflict));
+    }
+    scrollable(merge_column).into()
+  }
+
+  pub fn view(&self) -> Element<'_, ImportMessage> {
+    let description: &str = match self.format {
+      ImportFormat::Netscape => "Pick a bookmarks.html exported from a browser. Folders become tags, and links already in the library are skipped.",
+      ImportFormat::Reservoir => "Pick a library exported from reservoir. Bookmarks already in the library but changed in the file can be compared before anything is overwritten.",
+      ImportFormat::Firefox => "Pick places.sqlite in a Firefox profile folder (about:support shows where it is, tick Show hidden to get into folders like ~/.mozilla). Folders and tags become tags, links already in the library are skipped.",
+      ImportFormat::Chromium => "Pick the Bookmarks file in a Chrome, Chromium, Edge or Brave profile folder (chrome://version shows where it is, tick Show hidden to get into folders like ~/.config). Folders become tags, links already in the library are skipped.",
+      ImportFormat::Pocket => "Pick the part_000000.csv from Pocket's export zip (unzip it first). Links already in the library are skipped.",
+      ImportFormat::Pinboard => "Pick the JSON backup from Pinboard's settings. Descriptions become notes, links already in the library are skipped.",
+      ImportFormat::Raindrop => "Pick the CSV export from Raindrop. Collections become tags, notes and excerpts become notes, links already in the library are skipped.",
+    };
+    let mut import_column = column![
+      row![
+        pick_list(ImportFormat::all(), Some(self.format), ImportMessage::FormatChange),
+        text(description).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      ].spacing(10).align_items(Alignment::Center),
+    ].spacing(10);
+    if let Some(status) = &self.status {
+      import_column = import_column.push(text(status));
+    }
+    if let Some(preview) = &self.preview {
+      import_column = import_column.push(ImportView::preview_view(preview));
+    } else if let Some(merge) = &self.merge {
+      import_column = import_column.push(ImportView::merge_view(merge));
+    } else if !self.importing {
+      import_column = import_column.push(self.file_chooser.view().map(ImportMessage::Chooser));
+    }
+    column![
+      row![
+        button("Back").on_press(ImportMessage::Close),
+        container(text("Import").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      container(import_column).padding([0, 20, 10, 20]),
+    ].into()
+  }
+}
@@ -0,0 +1,528 @@
+use std::collections::HashMap;
+use std::fs::{ read_to_string, remove_file, rename, File, OpenOptions };
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use serde::{ Serialize, Deserialize };
+
+use crate::storage::{ migrate, Bookmark, BookmarkFilter, SavedSearch, Settings, Storage, StorageBackend, StorageError, Stored, TrashedBookmark, BACKUP_INTERVAL, SCHEMA_VERSION };
+use crate::utils::get_timestamp;
+
+//one line of the journal
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalRecord {
+  Upsert { bookmark: Bookmark },
+  Delete { uuid: String },
+  UpsertTrashed { trashed: TrashedBookmark },
+  DeleteTrashed { uuid: String },
+  Settings { settings: Settings },
+  SavedSearches { saved_searches: Vec<SavedSearch> },
+}
+
+struct JsonState {
+  //copy of the library, compaction writes all of it
+  stored: Option<Stored>,
+  //records not appended to the journal yet
+  unwritten: Vec<JournalRecord>,
+  //records in the journal file since the last compaction
+  journal_length: usize,
+  //the snapshot has to be rewritten (replace() was called)
+  rewrite: bool,
+  //when the journal was last written to, compaction waits until the library has been left alone for a while
+  last_commit: u64,
+  //when the last rolling backup was made, and whether the library changed since
+  last_backup: u64,
+  changed_since_backup: bool,
+}
+
+//the original backend. stored.json is a snapshot of the whole library, and every change after it is appended to
+//stored.journal, one json record per line. loading replays the journal on top of the snapshot, and once the
+//journal gets long, or nothing has changed for a while, it is compacted back into a new snapshot in the background.
+//so an edit only costs one line of i/o
+pub struct JsonStorage {
+  state: Mutex<JsonState>,
+}
+
+impl JsonStorage {
+  //compact during a save once the journal has this many records, so it can't grow without limit while edits keep coming
+  const COMPACT_AFTER: usize = 500;
+  //otherwise compact once nothing was saved for this many seconds
+  const COMPACT_IDLE: u64 = 60;
+
+  pub fn new() -> JsonStorage {
+    JsonStorage {
+      state: Mutex::new(JsonState {
+        stored: None,
+        unwritten: Vec::new(),
+        journal_length: 0,
+        rewrite: false,
+        last_commit: 0,
+        last_backup: 0,
+        changed_since_backup: false,
+      }),
+    }
+  }
+
+  pub fn path() -> PathBuf {
+    Storage::data_dir().join("stored.json")
+  }
+
+  fn journal_path() -> PathBuf {
+    Storage::data_dir().join("stored.journal")
+  }
+
+  fn temp_path() -> PathBuf {
+    JsonStorage::path().with_extension("json.tmp")
+  }
+
+  fn empty_json() -> String {
+    serde_json::to_string_pretty(&Stored::empty()).unwrap()
+  }
+
+  //parse a library file, migrating it if it is old. also returns the version it was migrated from, if it was
+  pub fn parse(contents: &str) -> Result<(Stored, Option<u32>), StorageError> {
+    JsonStorage::parse_with_journal(contents, &[])
+  }
+
+  //journal records are replayed as plain json before migrating, since they are in the format of the snapshot
+  fn parse_with_journal(contents: &str, journal: &[Value]) -> Result<(Stored, Option<u32>), StorageError> {
+    let mut value: Value = serde_json::from_str(contents).map_err(StorageError::from_json)?;
+    for record in journal {
+      let op: &str = record.get("op").and_then(Value::as_str).unwrap_or_default();
+      let (map_key, record_key): (&str, &str) = match op {
+        "upsert" | "delete" => ("bookmarks", "bookmark"),
+        "upsert_trashed" | "delete_trashed" => ("trash", "trashed"),
+        "settings" => {
+          value["settings"] = record["settings"].clone();
+          continue;
+        },
+        "saved_searches" => {
+          value["saved_searches"] = record["saved_searches"].clone();
+          continue;
+        },
+        _ => continue,
+      };
+      if let Value::Object(map) = &mut value[map_key] {
+        if op.starts_with("upsert") {
+          let uuid: String = record[record_key]["uuid"].as_str().unwrap_or_default().to_string();
+          map.insert(uuid, record[record_key].clone());
+        } else {
+          map.remove(record["uuid"].as_str().unwrap_or_default());
+        }
+      }
+    }
+    //files from before versioning don't have schema_version at all
+    let version: u32 = value.get("schema_version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+      return Err(StorageError::VersionError);
+    }
+    if version < SCHEMA_VERSION {
+      migrate(&mut value, version);
+      let stored: Stored = serde_json::from_value(value).map_err(StorageError::from_json)?;
+      return Ok((stored, Some(version)));
+    }
+    if journal.is_empty() {
+      //parse straight from the text rather than from value, so any error has a line and column
+      return Ok((serde_json::from_str(contents).map_err(StorageError::from_json)?, None));
+    }
+    Ok((serde_json::from_value(value).map_err(StorageError::from_json)?, None))
+  }
+
+  //every complete record in the journal. a crash while appending can leave a broken last line, which is dropped
+  fn read_journal() -> Result<Vec<Value>, StorageError> {
+    let journal_path: PathBuf = JsonStorage::journal_path();
+    if !journal_path.is_file() {
+      return Ok(Vec::new());
+    }
+    let contents: String = read_to_string(journal_path).map_err(|_| StorageError::ReadError)?;
+    let mut journal: Vec<Value> = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+      match serde_json::from_str(line) {
+        Ok(record) => journal.push(record),
+        Err(error) => {
+          println!("{:?}", error);
+          break;
+        },
+      }
+    }
+    Ok(journal)
+  }
+
+  fn append_journal(records: &[JournalRecord]) -> Result<(), StorageError> {
+    let mut lines: String = String::new();
+    for record in records {
+      lines.push_str(&serde_json::to_string(record).unwrap());
+      lines.push('\n');
+    }
+    let mut journal_file: File = OpenOptions::new().create(true).append(true).open(JsonStorage::journal_path()).map_err(|_| StorageError::OpenError)?;
+    journal_file.write_all(lines.as_bytes()).map_err(|_| StorageError::WriteError)?;
+    journal_file.sync_all().map_err(|_| StorageError::WriteError)?;
+    Ok(())
+  }
+
+  //write a new snapshot and empty the journal. the snapshot goes first, so a crash in between only means the
+  //journal gets replayed again, which changes nothing
+  fn compact(stored: &Stored) -> Result<(), StorageError> {
+    JsonStorage::write(stored)?;
+    if JsonStorage::journal_path().is_file() {
+      remove_file(JsonStorage::journal_path()).map_err(|_| StorageError::WriteError)?;
+    }
+    Ok(())
+  }
+
+  //a json copy of the library in the backups folder, the same format as stored.json
+  pub fn write_backup(stored: &Stored, prefix: &str) -> Result<(), StorageError> {
+    let mut backup_file: File = File::create(Storage::new_backup_path(prefix)?).map_err(|_| StorageError::CreateError)?;
+    backup_file.write_all(serde_json::to_string_pretty(stored).unwrap().as_bytes()).map_err(|_| StorageError::WriteError)?;
+    backup_file.sync_all().map_err(|_| StorageError::WriteError)?;
+    Ok(())
+  }
+
+  //keep an untouched copy of the file before migrating, in case a migration goes wrong
+  fn backup_before_migration(contents: &str, version: u32) -> Result<(), StorageError> {
+    let mut backup_file: File = File::create(Storage::new_backup_path(&format!("pre_migration_v{}", version))?).map_err(|_| StorageError::CreateError)?;
+    backup_file.write_all(contents.as_bytes()).map_err(|_| StorageError::WriteError)?;
+    backup_file.sync_all().map_err(|_| StorageError::WriteError)?;
+    Ok(())
+  }
+
+  //a rolling backup of the library as it is in memory (the snapshot alone misses whatever is in the journal).
+  //a failed backup shouldn't stop anything else though
+  fn backup(state: &mut JsonState) {
+    let Some(stored) = state.stored.as_ref() else {
+      return;
+    };
+    if let Err(error) = JsonStorage::write_backup(stored, "backup").and_then(|_| Storage::prune_backups()) {
+      println!("{:?}", error);
+    }
+    state.last_backup = get_timestamp();
+    state.changed_since_backup = false;
+  }
+
+  fn write(stored: &Stored) -> Result<(), StorageError> {
+    //write everything to a temp file first, then swap it in, so stored.json is never half written
+    let temp_path: PathBuf = JsonStorage::temp_path();
+    let mut temp_file: File = File::create(&temp_path).map_err(|_| StorageError::OpenError)?;
+    temp_file.write_all(serde_json::to_string_pretty(stored).unwrap().as_bytes()).map_err(|_| StorageError::WriteError)?;
+    temp_file.sync_all().map_err(|_| StorageError::WriteError)?;
+    drop(temp_file);
+    rename(&temp_path, JsonStorage::path()).map_err(|_| StorageError::WriteError)?;
+    //make the rename itself durable (not possible to open a directory like this on windows)
+    #[cfg(unix)]
+    if let Ok(save_dir) = File::open(Storage::data_dir()) {
+      let _ = save_dir.sync_all();
+    }
+    Ok(())
+  }
+
+  //after the library moved to another backend, put stored.json with the backups so it is never read again.
+  //the journal was already replayed into the library that got moved, so it can go
+  pub fn retire() -> Result<(), StorageError> {
+    let save_path: PathBuf = JsonStorage::path();
+    if save_path.is_file() {
+      rename(save_path, Storage::new_backup_path("pre_sqlite")?).map_err(|_| StorageError::WriteError)?;
+    }
+    if JsonStorage::journal_path().is_file() {
+      remove_file(JsonStorage::journal_path()).map_err(|_| StorageError::WriteError)?;
+    }
+    Ok(())
+  }
+
+  //pull every bookmark that is still intact out of a broken file
+  pub fn salvage() -> Result<Stored, StorageError> {
+    let contents: String = read_to_string(JsonStorage::path()).map_err(|_| StorageError::ReadError)?;
+    Ok(JsonStorage::salvage_contents(&contents))
+  }
+
+  //tries to read a bookmark starting at each {, and skips over whatever it managed to read
+  fn salvage_contents(contents: &str) -> Stored {
+    let mut bookmarks: HashMap<String, Bookmark> = HashMap::new();
+    let mut position: usize = 0;
+    while let Some(offset) = contents[position..].find('{') {
+      let start: usize = position + offset;
+      let mut stream = serde_json::Deserializer::from_str(&contents[start..]).into_iter::<Bookmark>();
+      match stream.next() {
+        Some(Ok(bookmark)) => {
+          position = start + stream.byte_offset();
+          bookmarks.insert(bookmark.uuid.clone(), bookmark);
+        },
+        _ => {
+          position = start + 1;
+        },
+      }
+    }
+    Stored {
+      bookmarks,
+      ..Stored::empty()
+    }
+  }
+
+  //the copy is only none if load failed and no recovery option has been picked yet
+  fn record(&self, record: JournalRecord, change: impl FnOnce(&mut Stored)) {
+    let mut state = self.state.lock().unwrap();
+    if let Some(stored) = state.stored.as_mut() {
+      change(stored);
+      state.unwritten.push(record);
+    }
+  }
+}
+
+impl StorageBackend for JsonStorage {
+  fn load(&self) -> Result<Stored, StorageError> {
+    Storage::create_data_dir()?;
+    let save_path: PathBuf = JsonStorage::path();
+    if !save_path.is_file() {
+      //create file
+      let mut save_file: File = File::create(&save_path).map_err(|_| StorageError::CreateError)?;
+      save_file.write_all(JsonStorage::empty_json().as_bytes()).map_err(|_| StorageError::WriteError)?;
+    }
+    let contents: String = read_to_string(save_path).map_err(|_| StorageError::ReadError)?;
+    let journal: Vec<Value> = JsonStorage::read_journal()?;
+    let (stored, migrated_from) = JsonStorage::parse_with_journal(&contents, &journal)?;
+    if let Some(version) = migrated_from {
+      JsonStorage::backup_before_migration(&contents, version)?;
+    }
+    if migrated_from.is_some() || JsonStorage::journal_path().is_file() {
+      //start from a clean snapshot, so migrations only ever run once, the journal doesn't pile up across runs,
+      //and new records never end up after a broken line
+      JsonStorage::compact(&stored)?;
+    }
+    let mut state = self.state.lock().unwrap();
+    state.stored = Some(stored.clone());
+    state.unwritten = Vec::new();
+    state.journal_length = 0;
+    state.rewrite = false;
+    //the library as it was opened, before anything this session changes it
+    state.last_backup = Storage::last_backup_time();
+    if get_timestamp().saturating_sub(state.last_backup) >= BACKUP_INTERVAL {
+      JsonStorage::backup(&mut state);
+    }
+    Ok(stored)
+  }
+
+  fn upsert_bookmark(&self, bookmark: &Bookmark) -> Result<(), StorageError> {
+    self.record(JournalRecord::Upsert { bookmark: bookmark.clone() }, |stored| {
+      stored.bookmarks.insert(bookmark.uuid.clone(), bookmark.clone());
+    });
+    Ok(())
+  }
+
+  fn delete_bookmark(&self, uuid: &str) -> Result<(), StorageError> {
+    self.record(JournalRecord::Delete { uuid: uuid.to_string() }, |stored| {
+      stored.bookmarks.remove(uuid);
+    });
+    Ok(())
+  }
+
+  fn upsert_trashed(&self, trashed: &TrashedBookmark) -> Result<(), StorageError> {
+    self.record(JournalRecord::UpsertTrashed { trashed: trashed.clone() }, |stored| {
+      stored.trash.insert(trashed.bookmark.uuid.clone(), trashed.clone());
+    });
+    Ok(())
+  }
+
+  fn delete_trashed(&self, uuid: &str) -> Result<(), StorageError> {
+    self.record(JournalRecord::DeleteTrashed { uuid: uuid.to_string() }, |stored| {
+      stored.trash.remove(uuid);
+    });
+    Ok(())
+  }
+
+  fn save_settings(&self, settings: &Settings) -> Result<(), StorageError> {
+    self.record(JournalRecord::Settings { settings: settings.clone() }, |stored| {
+      stored.settings = settings.clone();
+    });
+    Ok(())
+  }
+
+  fn save_saved_searches(&self, saved_searches: &[SavedSearch]) -> Result<(), StorageError> {
+    self.record(JournalRecord::SavedSearches { saved_searches: saved_searches.to_vec() }, |stored| {
+      stored.saved_searches = saved_searches.to_vec();
+    });
+    Ok(())
+  }
+
+  //the whole library is in memory anyway
+  fn query(&self, filter: &BookmarkFilter) -> Result<Vec<Bookmark>, StorageError> {
+    let state = self.state.lock().unwrap();
+    Ok(state.stored.iter().flat_map(|stored| stored.bookmarks.values()).filter(|bookmark| filter.matches(bookmark)).cloned().collect())
+  }
+
+  fn replace(&self, stored: &Stored) -> Result<(), StorageError> {
+    let mut state = self.state.lock().unwrap();
+    state.stored = Some(stored.clone());
+    //the snapshot is rewritten anyway, journaling every bookmark would be pointless
+    state.unwritten = Vec::new();
+    state.rewrite = true;
+    Ok(())
+  }
+
+  fn commit(&self) -> Result<(), StorageError> {
+    let mut state = self.state.lock().unwrap();
+    if state.stored.is_none() {
+      return Ok(());
+    }
+    Storage::create_data_dir()?;
+    if state.rewrite || state.journal_length + state.unwritten.len() > JsonStorage::COMPACT_AFTER {
+      JsonStorage::compact(state.stored.as_ref().unwrap())?;
+      state.journal_length = 0;
+      state.rewrite = false;
+    } else if !state.unwritten.is_empty() {
+      JsonStorage::append_journal(&state.unwritten)?;
+      state.journal_length += state.unwritten.len();
+    }
+    state.unwritten = Vec::new();
+    state.last_commit = get_timestamp();
+    state.changed_since_backup = true;
+    Ok(())
+  }
+
+  fn maintain(&self) -> Result<(), StorageError> {
+    let mut state = self.state.lock().unwrap();
+    if state.stored.is_none() {
+      return Ok(());
+    }
+    let now: u64 = get_timestamp();
+    if state.journal_length > 0 && now.saturating_sub(state.last_commit) >= JsonStorage::COMPACT_IDLE {
+      JsonStorage::compact(state.stored.as_ref().unwrap())?;
+      state.journal_length = 0;
+    }
+    if state.changed_since_backup && now.saturating_sub(state.last_backup) >= BACKUP_INTERVAL {
+      JsonStorage::backup(&mut state);
+    }
+    Ok(())
+  }
+
+  //stored_corrupt_<timestamp>.json, next to where it was
+  fn move_aside(&self) -> Result<(), StorageError> {
+    let timestamp: u64 = get_timestamp();
+    let save_path: PathBuf = JsonStorage::path();
+    if save_path.is_file() {
+      rename(&save_path, save_path.with_file_name(format!("stored_corrupt_{}.json", timestamp))).map_err(|_| StorageError::WriteError)?;
+    }
+    //the journal belongs to that snapshot, replaying it onto a different one makes no sense
+    let journal_path: PathBuf = JsonStorage::journal_path();
+    if journal_path.is_file() {
+      rename(&journal_path, journal_path.with_file_name(format!("stored_corrupt_{}.journal", timestamp))).map_err(|_| StorageError::WriteError)?;
+    }
+    Ok(())
+  }
+
+  fn describe(&self) -> String {
+    format!("JSON file at {}", JsonStorage::path().display())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn bookmark(uuid: &str, tags: &[&str]) -> Bookmark {
+    Bookmark {
+      title: format!("Bookmark {}", uuid),
+      link: format!("https://example.com/{}", uuid),
+      note: None,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      uuid: uuid.to_string(),
+      timestamp: 1,
+    }
+  }
+
+  fn snapshot(bookmarks: &[Bookmark]) -> String {
+    serde_json::to_string(&Stored {
+      bookmarks: bookmarks.iter().map(|bookmark| (bookmark.uuid.clone(), bookmark.clone())).collect(),
+      ..Stored::empty()
+    }).unwrap()
+  }
+
+  fn journal(records: Vec<JournalRecord>) -> Vec<Value> {
+    records.iter().map(|record| serde_json::to_value(record).unwrap()).collect()
+  }
+
+  #[test]
+  fn replays_the_journal_over_the_snapshot() {
+    let trashed: TrashedBookmark = TrashedBookmark {
+      bookmark: bookmark("b", &[]),
+      deleted: 5,
+    };
+    let settings: Settings = Settings {
+      trash_days: 3,
+      ..Settings::default()
+    };
+    let records: Vec<Value> = journal(vec![
+      JournalRecord::Upsert { bookmark: bookmark("a", &["changed"]) },
+      JournalRecord::Upsert { bookmark: bookmark("c", &[]) },
+      JournalRecord::Delete { uuid: "b".to_string() },
+      JournalRecord::UpsertTrashed { trashed: trashed.clone() },
+      JournalRecord::Settings { settings: settings.clone() },
+      JournalRecord::SavedSearches { saved_searches: Vec::new() },
+    ]);
+    let (stored, migrated_from) = JsonStorage::parse_with_journal(&snapshot(&[bookmark("a", &[]), bookmark("b", &[])]), &records).unwrap();
+    assert_eq!(migrated_from, None);
+    let mut uuids: Vec<&String> = stored.bookmarks.keys().collect();
+    uuids.sort();
+    assert_eq!(uuids, vec!["a", "c"]);
+    assert_eq!(stored.bookmarks["a"].tags, vec!["changed"]);
+    assert_eq!(stored.trash["b"], trashed);
+    assert_eq!(stored.settings, settings);
+  }
+
+  #[test]
+  fn later_records_win() {
+    let records: Vec<Value> = journal(vec![
+      JournalRecord::Delete { uuid: "a".to_string() },
+      JournalRecord::Upsert { bookmark: bookmark("a", &["back"]) },
+      JournalRecord::UpsertTrashed { trashed: TrashedBookmark { bookmark: bookmark("b", &[]), deleted: 5 } },
+      JournalRecord::DeleteTrashed { uuid: "b".to_string() },
+    ]);
+    let (stored, _) = JsonStorage::parse_with_journal(&snapshot(&[bookmark("a", &[])]), &records).unwrap();
+    assert_eq!(stored.bookmarks["a"].tags, vec!["back"]);
+    assert!(stored.trash.is_empty());
+  }
+
+  #[test]
+  fn skips_records_it_doesnt_know() {
+    let records: Vec<Value> = vec![serde_json::json!({ "op": "something_new", "uuid": "a" })];
+    let (stored, _) = JsonStorage::parse_with_journal(&snapshot(&[bookmark("a", &[])]), &records).unwrap();
+    assert!(stored.bookmarks.contains_key("a"));
+  }
+
+  #[test]
+  fn migrates_the_journal_with_an_old_snapshot() {
+    let mut value: Value = serde_json::from_str(&snapshot(&[])).unwrap();
+    value["schema_version"] = Value::from(5);
+    let records: Vec<Value> = journal(vec![JournalRecord::Upsert { bookmark: bookmark("a", &[" lang / rust ", "Lang/Rust"]) }]);
+    let (stored, migrated_from) = JsonStorage::parse_with_journal(&value.to_string(), &records).unwrap();
+    assert_eq!(migrated_from, Some(5));
+    assert_eq!(stored.schema_version, SCHEMA_VERSION);
+    assert_eq!(stored.bookmarks["a"].tags, vec!["lang/rust"]);
+  }
+
+  #[test]
+  fn refuses_a_newer_library() {
+    let mut value: Value = serde_json::from_str(&snapshot(&[])).unwrap();
+    value["schema_version"] = Value::from(SCHEMA_VERSION + 1);
+    assert!(matches!(JsonStorage::parse(&value.to_string()), Err(StorageError::VersionError)));
+  }
+
+  #[test]
+  fn salvages_the_intact_bookmarks() {
+    let contents: String = serde_json::to_string_pretty(&Stored {
+      bookmarks: [bookmark("a", &["x"]), bookmark("b", &[])].into_iter().map(|bookmark| (bookmark.uuid.clone(), bookmark)).collect(),
+      ..Stored::empty()
+    }).unwrap();
+    //cut off in the middle of the second bookmark, with some garbage after it
+    let cut: usize = contents.rfind("\"link\"").unwrap();
+    let broken: String = format!("{}{{ not json", &contents[..cut]);
+    let stored: Stored = JsonStorage::salvage_contents(&broken);
+    assert_eq!(stored.bookmarks.len(), 1);
+    assert!(JsonStorage::parse(&broken).is_err());
+    let intact: &Bookmark = stored.bookmarks.values().next().unwrap();
+    let original: Bookmark = if intact.uuid == "a" { bookmark("a", &["x"]) } else { bookmark("b", &[]) };
+    assert_eq!(*intact, original);
+    assert_eq!(stored.schema_version, SCHEMA_VERSION);
+  }
+}
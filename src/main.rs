@@ -1,29 +1,79 @@
 #![windows_subsystem = "windows"]
 
+use std::path::{ Path, PathBuf };
 use std::time::Duration;
 
 //use iced::futures::FutureExt;
 use iced::{ Application, Element };
-use iced::{ alignment, Command, Length, Settings, subscription, Subscription, window };
+use iced::{ alignment, event, keyboard, Alignment, Command, Length, Settings, subscription, Subscription, window };
 use iced::theme::Theme;
-use iced::widget::{ container, column, text };
+use iced::widget::{ button, container, column, row, text };
 use image::ImageFormat;
 
 use async_std::task;
 
 mod utils;
+use utils::{ link_scheme, open_path };
+
+mod history;
+use history::History;
 
 mod styles;
 
 mod storage;
 use storage::{ Stored, StorageError, Storage };
 
+mod json_storage;
+
+mod sqlite_storage;
+
+mod query;
+
+mod relevance;
+
+mod fuzzy;
+
+mod search_index;
+
+mod tags;
+
 mod bookmark_bar;
-use bookmark_bar::{ BarMessage, BookmarkBar, SearchOptions };
+use bookmark_bar::{ BarMessage, BookmarkBar, ExportScope, Page };
 
 mod bookmark_list;
 use bookmark_list::{ ListMessage, BookmarkList };
 
+mod backup_list;
+use backup_list::{ BackupMessage, BackupList };
+
+mod settings_view;
+use settings_view::{ SettingsMessage, SettingsView };
+
+mod trash_list;
+use trash_list::{ TrashMessage, TrashList };
+
+mod duplicate_list;
+use duplicate_list::{ DuplicateMessage, DuplicateList };
+
+mod saved_search_list;
+use saved_search_list::{ SavedSearchMessage, SavedSearchList };
+
+mod tag_manager;
+use tag_manager::{ TagManagerMessage, TagManager };
+
+mod file_chooser;
+
+mod import;
+use import::ImportFormat;
+
+mod export;
+
+mod export_view;
+use export_view::{ ExportMessage, ExportView };
+
+mod import_view;
+use import_view::{ ImportMessage, ImportView };
+
 fn main() -> iced::Result {
   App::run(Settings {
     window: window::Settings {
@@ -36,6 +86,13 @@ fn main() -> iced::Result {
   })
 }
 
+//the banner under the bar (export finished, bookmark deleted, etc)
+struct Notice {
+  text: String,
+  //show an undo button
+  undo: bool,
+}
+
 pub struct WindowSize {
   pub width: u32,
   pub height: u32,
@@ -43,25 +100,141 @@ pub struct WindowSize {
 
 struct App {
   pub storage: Storage,
+  history: History,
   loaded: bool,
+  load_error: Option<StorageError>,
   bookmark_list: BookmarkList,
   bookmark_bar: BookmarkBar,
+  backup_list: BackupList,
+  settings_view: SettingsView,
+  trash_list: TrashList,
+  duplicate_list: DuplicateList,
+  saved_search_list: SavedSearchList,
+  tag_manager: TagManager,
+  import_view: ImportView,
+  export_view: ExportView,
+  page: Page,
   window_size: WindowSize,
-  save_message_count: u16,
-  save_message: bool,
+  notice_count: u16,
+  notice: Option<Notice>,
+  //counts keystrokes in boxes that change the library, so only the last one of a burst saves
+  typing_count: u16,
 }
 
 #[derive(Clone, Debug)]
 enum AppMessage {
   Loaded(Result<Stored, StorageError>),
+  RestoreBackup,
+  SalvageBookmarks,
+  StartFresh,
+  OpenDataDir,
   BarMessage(BarMessage),
   ListMessage(ListMessage),
+  BackupMessage(BackupMessage),
+  SettingsMessage(SettingsMessage),
+  TrashMessage(TrashMessage),
+  DuplicateMessage(DuplicateMessage),
+  SavedSearchMessage(SavedSearchMessage),
+  TagManagerMessage(TagManagerMessage),
+  ImportMessage(ImportMessage),
+  ExportMessage(ExportMessage),
   SaveDone(Result<(), StorageError>),
-  ExportDone(Result<(), StorageError>),
-  HideExportDone(u16),
+  TypingPaused(u16),
+  Maintain,
+  MaintainDone(Result<(), StorageError>),
+  HideNotice(u16),
+  Undo,
+  Redo,
   SizeChange(u32, u32),
 }
 
+impl App {
+  //typing changes the library right away, but is recorded as one undo step per box and saved once it stops for a moment
+  fn typed(&mut self, field: String) -> Command<AppMessage> {
+    self.history.record_typing(&mut self.storage, field);
+    self.refresh_searches();
+    self.typing_count = self.typing_count.wrapping_add(1);
+    let typing_count: u16 = self.typing_count;
+    Command::perform(task::sleep(Duration::from_secs(1)), move |_| AppMessage::TypingPaused(typing_count))
+  }
+
+  //storage housekeeping (compaction, backups) runs this often, in the background
+  fn schedule_maintenance() -> Command<AppMessage> {
+    Command::perform(task::sleep(Duration::from_secs(30)), |_| AppMessage::Maintain)
+  }
+
+  //run the current search and the saved ones again, after the bookmarks changed
+  fn refresh_searches(&mut self) {
+    self.bookmark_list.refresh(&self.storage);
+    self.bookmark_bar.refresh_saved_counts(&self.storage);
+    if self.page == Page::Duplicates {
+      self.duplicate_list.refresh(&self.storage);
+    } else if self.page == Page::Tags {
+      self.tag_manager.refresh(&self.storage);
+    }
+  }
+
+  //start saving whatever changed, unless a save is already running (SaveDone starts the next one then)
+  fn save(&mut self) -> Command<AppMessage> {
+    //whatever changed since the last save is one undo step
+    self.history.record(&mut self.storage);
+    self.refresh_searches();
+    match self.storage.save() {
+      Some(save) => Command::perform(save, AppMessage::SaveDone),
+      None => Command::none(),
+    }
+  }
+
+  //permanently delete whatever has been in the trash too long. that isn't something to undo, so it stays out of the history
+  fn purge_trash(&mut self) -> Command<AppMessage> {
+    self.history.record(&mut self.storage);
+    if self.storage.purge_expired() {
+      self.storage.take_changes();
+      self.save()
+    } else {
+      Command::none()
+    }
+  }
+
+  //what the export picker in the bar says to export
+  fn export_selection(&self) -> Stored {
+    let stored: &Stored = self.storage.stored.as_ref().unwrap();
+    match self.bookmark_bar.bookmark_export.scope {
+      ExportScope::All => stored.to_owned(),
+      ExportScope::Filtered => Stored {
+        bookmarks: self.bookmark_list.shown(&stored.bookmarks).into_iter().map(|bookmark| (bookmark.uuid.clone(), bookmark.clone())).collect(),
+        ..Stored::empty()
+      },
+    }
+  }
+
+  //start the next export in the same folder. like purging, not something to undo
+  fn remember_export_dir(&mut self, path: &Path) -> Command<AppMessage> {
+    let export_dir: Option<PathBuf> = path.parent().map(Path::to_path_buf);
+    let mut settings: storage::Settings = self.storage.stored.as_ref().unwrap().settings.clone();
+    if settings.export_dir == export_dir {
+      return Command::none();
+    }
+    settings.export_dir = export_dir;
+    self.history.record(&mut self.storage);
+    self.storage.set_settings(settings);
+    self.storage.take_changes();
+    self.save()
+  }
+
+  fn show_notice(&mut self, text: &str, undo: bool) -> Command<AppMessage> {
+    self.notice = Some(Notice {
+      text: text.to_string(),
+      undo,
+    });
+    self.notice_count += 1;
+    let notice_count: u16 = self.notice_count;
+    //give people a bit longer to reach the undo button
+    let duration: Duration = Duration::from_secs(if undo { 6 } else { 2 });
+    Command::perform(task::sleep(duration), move |_| AppMessage::HideNotice(notice_count))
+  }
+}
+
 //all a big placeholder for now
 impl Application for App {
   type Executor = iced::executor::Default;
@@ -70,20 +243,34 @@ impl Application for App {
   type Flags = ();
   
   fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    let storage: Storage = Storage::new();
+    let load = storage.load();
     (
       App {
-        storage: Storage::new(),
+        storage,
+        history: History::new(),
         loaded: false,
+        load_error: None,
         bookmark_list: BookmarkList::new(),
         bookmark_bar: BookmarkBar::new(),
+        backup_list: BackupList::new(),
+        settings_view: SettingsView::new(),
+        trash_list: TrashList::new(),
+        duplicate_list: DuplicateList::new(),
+        saved_search_list: SavedSearchList::new(),
+        tag_manager: TagManager::new(),
+        import_view: ImportView::new(),
+        export_view: ExportView::new(),
+        page: Page::Bookmarks,
         window_size: WindowSize {
           width: 920,
           height: 600,
         },
-        save_message: false,
-        save_message_count: 0,
+        notice: None,
+        notice_count: 0,
+        typing_count: 0,
       },
-      Command::perform(Storage::load(), Self::Message::Loaded),
+      Command::batch([Command::perform(load, Self::Message::Loaded), App::schedule_maintenance()]),
     )
   }
 
@@ -98,59 +285,262 @@ impl Application for App {
   fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
     match message {
       Self::Message::Loaded(Ok(stored)) => {
-        self.storage.stored = Some(stored);
+        self.storage.loaded(stored);
+        self.refresh_searches();
         self.loaded = true;
+        self.load_error = None;
+        self.purge_trash()
+      },
+      Self::Message::Loaded(Err(error)) => {
+        self.load_error = Some(error);
+        Command::none()
+      },
+      Self::Message::RestoreBackup => {
+        Command::perform(self.storage.restore_latest_backup(), Self::Message::Loaded)
+      },
+      Self::Message::SalvageBookmarks => {
+        Command::perform(self.storage.salvage(), Self::Message::Loaded)
+      },
+      Self::Message::StartFresh => {
+        Command::perform(self.storage.start_fresh(), Self::Message::Loaded)
+      },
+      Self::Message::OpenDataDir => {
+        if let Err(error) = open_path(&Storage::data_dir()) {
+          println!("{:?}", error);
+        }
         Command::none()
       },
       Self::Message::BarMessage(message) => {
         self.bookmark_bar.update(message.clone(), &mut self.storage);
         if BarMessage::is_save_after(message.clone()) {
           //self.storage.save_sync();
-          Command::perform(Storage::save_async_separate(self.storage.stored.as_ref().unwrap().to_owned()), AppMessage::SaveDone)
-        } else if message == BarMessage::ExportAll {
-          Command::perform(Storage::export(self.storage.stored.as_ref().unwrap().to_owned()), AppMessage::ExportDone)
+          self.save()
+        } else if let BarMessage::ShowPage(page) = message {
+          self.page = page;
+          if page == Page::Backups {
+            self.update(Self::Message::BackupMessage(BackupMessage::Refresh))
+          } else if page == Page::Trash {
+            self.purge_trash()
+          } else if page == Page::Duplicates {
+            self.duplicate_list.refresh(&self.storage);
+            Command::none()
+          } else if page == Page::Tags {
+            self.tag_manager.refresh(&self.storage);
+            Command::none()
+          } else {
+            Command::none()
+          }
+        } else if message == BarMessage::Export {
+          self.export_view.open(self.storage.export_dir(), self.bookmark_bar.bookmark_export.format);
+          self.page = Page::Export;
+          Command::none()
         } else {
           if message == BarMessage::ExpandAll {
             self.bookmark_list.update(ListMessage::ExpandAll, &mut self.storage);
           } else if message == BarMessage::ShrinkAll {
             self.bookmark_list.update(ListMessage::ShrinkAll, &mut self.storage);
           } else if BarMessage::is_search_update(message) {
-            self.bookmark_list.update(ListMessage::UpdateSearch(self.bookmark_bar.bookmark_search.search_option, self.bookmark_bar.bookmark_search.sort_option, self.bookmark_bar.input_values.get("search").cloned()), &mut self.storage);
+            self.bookmark_list.update(ListMessage::UpdateSearch(self.bookmark_bar.query.clone(), self.bookmark_bar.bookmark_search.sort_option, self.bookmark_bar.bookmark_search.search_mode), &mut self.storage);
           }
           Command::none()
         }
       },
+      Self::Message::ListMessage(ListMessage::OpenLink(link)) if !self.storage.can_open(&link) => {
+        let scheme: String = link_scheme(&link).unwrap_or_default();
+        self.show_notice(&format!("{}: links aren't opened, they can be allowed in Settings", scheme), false)
+      },
       Self::Message::ListMessage(message) => {
         self.bookmark_list.update(message.clone(), &mut self.storage);
-        if ListMessage::is_save_after(message.clone()) {
+        if matches!(message, ListMessage::DeleteBookmark(_)) {
+          let save: Command<AppMessage> = self.save();
+          Command::batch([save, self.show_notice("Bookmark moved to trash", true)])
+        } else if ListMessage::is_save_after(message.clone()) {
           //self.storage.save_sync();
-          Command::perform(Storage::save_async_separate(self.storage.stored.as_ref().unwrap().to_owned()), AppMessage::SaveDone)
+          self.save()
         } else {
-          if let ListMessage::TagPress(tag) = message {
-            self.bookmark_bar.update(BarMessage::ShowSearch, &mut self.storage);
-            self.bookmark_bar.update(BarMessage::SearchOptionChange(SearchOptions::Tags), &mut self.storage);
-            self.bookmark_bar.update(BarMessage::InputSet("search".to_string(), tag), &mut self.storage);
-            self.bookmark_list.update(ListMessage::UpdateSearch(self.bookmark_bar.bookmark_search.search_option, self.bookmark_bar.bookmark_search.sort_option, self.bookmark_bar.input_values.get("search").cloned()), &mut self.storage);
+          Command::none()
+        }
+      },
+      Self::Message::BackupMessage(message) => {
+        self.backup_list.update(message.clone(), &mut self.storage);
+        if BackupMessage::is_save_after(&message) {
+          self.page = Page::Bookmarks;
+          self.save()
+        } else {
+          match message {
+            BackupMessage::Refresh => {
+              Command::perform(Storage::list_backups(), |result| Self::Message::BackupMessage(BackupMessage::Listed(result)))
+            },
+            BackupMessage::Select(path) => {
+              Command::perform(Storage::read_backup(path.clone()), move |result| Self::Message::BackupMessage(BackupMessage::Selected(path.clone(), result.map(Box::new))))
+            },
+            BackupMessage::Close => {
+              self.page = Page::Bookmarks;
+              Command::none()
+            },
+            _ => Command::none(),
           }
+        }
+      },
+      Self::Message::SettingsMessage(message) => {
+        self.settings_view.update(message.clone(), &mut self.storage);
+        match message {
+          SettingsMessage::MigrateToSqlite => {
+            match self.storage.migrate_to_sqlite() {
+              Some(migration) => Command::perform(migration, |result| Self::Message::SettingsMessage(SettingsMessage::MigrateDone(result))),
+              None => Command::none(),
+            }
+          },
+          SettingsMessage::MigrateDone(result) => {
+            self.storage.migrate_to_sqlite_done(&result);
+            //anything changed during the migration still needs writing, now to the database
+            self.save()
+          },
+          SettingsMessage::Close => {
+            self.page = Page::Bookmarks;
+            Command::none()
+          },
+          _ => match SettingsMessage::typing_field(&message) {
+            Some(field) => self.typed(field),
+            None if SettingsMessage::is_save_after(&message) => self.save(),
+            None => Command::none(),
+          },
+        }
+      },
+      Self::Message::TrashMessage(message) => {
+        self.trash_list.update(message.clone(), &mut self.storage);
+        if TrashMessage::is_save_after(&message) {
+          self.save()
+        } else {
+          self.page = Page::Bookmarks;
           Command::none()
         }
       },
-      Self::Message::SaveDone(Err(error)) => {
-        println!("{:?}", error);
-        Command::none()
+      Self::Message::DuplicateMessage(message) => {
+        self.duplicate_list.update(message.clone(), &mut self.storage);
+        if DuplicateMessage::is_save_after(&message) {
+          self.save()
+        } else {
+          self.page = Page::Bookmarks;
+          Command::none()
+        }
+      },
+      Self::Message::SavedSearchMessage(message) => {
+        self.saved_search_list.update(message.clone(), &mut self.storage);
+        if let Some(field) = SavedSearchMessage::typing_field(&message) {
+          self.typed(field)
+        } else if SavedSearchMessage::is_save_after(&message) {
+          self.save()
+        } else {
+          self.page = Page::Bookmarks;
+          Command::none()
+        }
+      },
+      Self::Message::TagManagerMessage(message) => {
+        self.tag_manager.update(message.clone(), &mut self.storage);
+        if TagManagerMessage::is_save_after(&message) {
+          self.save()
+        } else {
+          if matches!(message, TagManagerMessage::Close) {
+            self.page = Page::Bookmarks;
+          }
+          Command::none()
+        }
       },
-      Self::Message::ExportDone(Ok(_)) => {
-        self.save_message = true;
-        self.save_message_count += 1;
-        let save_message_count: u16 = self.save_message_count;
-        Command::perform(task::sleep(Duration::from_secs(2)), move |_| Self::Message::HideExportDone(save_message_count))
+      Self::Message::ImportMessage(message) => {
+        let chosen: Option<PathBuf> = self.import_view.chosen(&message);
+        self.import_view.update(message.clone(), &mut self.storage);
+        if ImportMessage::is_save_after(&message) {
+          self.save()
+        } else if let Some(path) = chosen {
+          match self.import_view.format {
+            ImportFormat::Netscape => Command::perform(import::read_netscape(path), |result| Self::Message::ImportMessage(ImportMessage::Parsed(result))),
+            ImportFormat::Reservoir => Command::perform(import::read_reservoir(path), |result| Self::Message::ImportMessage(ImportMessage::Read(result.map(Box::new)))),
+            ImportFormat::Firefox => Command::perform(import::read_firefox(path), |result| Self::Message::ImportMessage(ImportMessage::Parsed(result))),
+            ImportFormat::Chromium => Command::perform(import::read_chromium(path), |result| Self::Message::ImportMessage(ImportMessage::Parsed(result))),
+            format => Command::perform(import::read_service_export(path, format), |result| Self::Message::ImportMessage(ImportMessage::Parsed(result))),
+          }
+        } else {
+          match message {
+            ImportMessage::Close => {
+              self.page = Page::Bookmarks;
+              Command::none()
+            },
+            _ => Command::none(),
+          }
+        }
       },
-      Self::Message::HideExportDone(save_message_count) => {
-        if save_message_count == self.save_message_count {
-          self.save_message = false;
+      Self::Message::ExportMessage(message) => {
+        let chosen: Option<PathBuf> = self.export_view.chosen(&message);
+        self.export_view.update(message.clone(), &mut self.storage);
+        if let Some(path) = chosen {
+          let export = Storage::export(self.export_selection(), self.bookmark_bar.bookmark_export.format, path);
+          return Command::perform(export, |result| Self::Message::ExportMessage(ExportMessage::Done(result)));
+        }
+        match message {
+          ExportMessage::Done(Ok(path)) => {
+            self.page = Page::Bookmarks;
+            //only a folder that was exported to is worth starting in next time
+            let remember: Command<AppMessage> = self.remember_export_dir(&path);
+            Command::batch([remember, self.show_notice(&format!("Exported to {}", path.display()), false)])
+          },
+          ExportMessage::Close => {
+            self.page = Page::Bookmarks;
+            Command::none()
+          },
+          _ => Command::none(),
+        }
+      },
+      Self::Message::SaveDone(result) => {
+        self.storage.save_done(&result);
+        if let Err(error) = result {
+          println!("{:?}", error);
+        }
+        //changes made while that save was running
+        self.save()
+      },
+      Self::Message::TypingPaused(typing_count) => {
+        if typing_count == self.typing_count {
+          self.save()
+        } else {
+          Command::none()
+        }
+      },
+      Self::Message::Maintain => {
+        match self.storage.maintain() {
+          Some(maintain) => Command::perform(maintain, AppMessage::MaintainDone),
+          None => App::schedule_maintenance(),
+        }
+      },
+      Self::Message::MaintainDone(result) => {
+        if let Err(error) = result {
+          println!("{:?}", error);
+        }
+        App::schedule_maintenance()
+      },
+      Self::Message::HideNotice(notice_count) => {
+        if notice_count == self.notice_count {
+          self.notice = None;
         }
         Command::none()
       },
+      Self::Message::Undo => {
+        let undone: bool = self.loaded && self.history.undo(&mut self.storage);
+        if undone {
+          self.notice = None;
+          self.save()
+        } else {
+          Command::none()
+        }
+      },
+      Self::Message::Redo => {
+        let redone: bool = self.loaded && self.history.redo(&mut self.storage);
+        if redone {
+          self.save()
+        } else {
+          Command::none()
+        }
+      },
       Self::Message::SizeChange(width, height) => {
         self.window_size = WindowSize { 
           width,
@@ -158,50 +548,84 @@ impl Application for App {
         };
         Command::none()
       },
-      _ => {
-        Command::none()
-      },
     }
   }
 
   //"view called when state is modified"
   fn view(&self) -> Element<'_, Self::Message> {
     //println!("Rerendering");
-    if self.loaded {
-      //something something DRY. don't care right now
-      if self.save_message {
-        column![
-          self.bookmark_bar.view().map(move |message| {
-            Self::Message::BarMessage(message)
-          }),
-          container(
-            text("Exported!").horizontal_alignment(alignment::Horizontal::Center)
-          ).width(Length::Fill).align_x(alignment::Horizontal::Center),
-          self.bookmark_list.view(&self.storage.stored.as_ref().unwrap().bookmarks, &self.window_size).map(move |message| {
-            Self::Message::ListMessage(message)
-          }),
-        ].into()
-      } else {
-        column![
-          self.bookmark_bar.view().map(move |message| {
-            Self::Message::BarMessage(message)
-          }),
-          self.bookmark_list.view(&self.storage.stored.as_ref().unwrap().bookmarks, &self.window_size).map(move |message| {
-            Self::Message::ListMessage(message)
-          }),
-        ].into()
+    if self.loaded && self.page == Page::Backups {
+      self.backup_list.view(&self.storage.stored.as_ref().unwrap().bookmarks).map(Self::Message::BackupMessage)
+    } else if self.loaded && self.page == Page::Trash {
+      let stored: &Stored = self.storage.stored.as_ref().unwrap();
+      self.trash_list.view(&stored.trash, &stored.settings).map(Self::Message::TrashMessage)
+    } else if self.loaded && self.page == Page::Duplicates {
+      self.duplicate_list.view().map(Self::Message::DuplicateMessage)
+    } else if self.loaded && self.page == Page::SavedSearches {
+      self.saved_search_list.view(&self.storage, &self.bookmark_bar.saved_counts).map(Self::Message::SavedSearchMessage)
+    } else if self.loaded && self.page == Page::Tags {
+      self.tag_manager.view().map(Self::Message::TagManagerMessage)
+    } else if self.loaded && self.page == Page::Import {
+      self.import_view.view().map(Self::Message::ImportMessage)
+    } else if self.loaded && self.page == Page::Export {
+      self.export_view.view().map(Self::Message::ExportMessage)
+    } else if self.loaded && self.page == Page::Settings {
+      self.settings_view.view(&self.storage).map(Self::Message::SettingsMessage)
+    } else if self.loaded {
+      let mut main_column = column![
+        self.bookmark_bar.view(&self.storage.stored.as_ref().unwrap().saved_searches).map(move |message| {
+          Self::Message::BarMessage(message)
+        }),
+      ];
+      if let Some(notice) = &self.notice {
+        let mut notice_row = row![
+          text(&notice.text).horizontal_alignment(alignment::Horizontal::Center),
+        ].spacing(10).align_items(Alignment::Center);
+        if notice.undo {
+          notice_row = notice_row.push(button("Undo").on_press(Self::Message::Undo));
+        }
+        main_column = main_column.push(container(notice_row).width(Length::Fill).align_x(alignment::Horizontal::Center));
       }
+      main_column.push(
+        self.bookmark_list.view(&self.storage.stored.as_ref().unwrap().bookmarks, &self.storage.stored.as_ref().unwrap().settings, &self.window_size).map(move |message| {
+          Self::Message::ListMessage(message)
+        }),
+      ).into()
+    } else if let Some(load_error) = &self.load_error {
+      //loading failed, let the user pick how to recover instead of being stuck on "Loading..."
+      container(
+        column![
+          text("Your bookmarks could not be loaded").size(24),
+          text(load_error.to_string()),
+          text(format!("The library is kept in {}. Any option below moves the current file aside instead of deleting it.", Storage::data_dir().display())),
+          row![
+            button("Restore Latest Backup").on_press(Self::Message::RestoreBackup),
+            button("Salvage Bookmarks").on_press(Self::Message::SalvageBookmarks),
+            button("Open File Location").on_press(Self::Message::OpenDataDir),
+            button("Start Fresh").on_press(Self::Message::StartFresh).style(iced::theme::Button::Destructive),
+          ].spacing(5),
+        ].spacing(10)
+      ).padding(20).into()
     } else {
       container("Loading...").padding(5).into()
     }
   }
 
   fn subscription(&self) -> Subscription<Self::Message> {
-    subscription::events_with(|event, _status| {
+    subscription::events_with(|event, status| {
       match event {
         iced::Event::Window(window::Event::Resized { width, height }) => {
           Some(AppMessage::SizeChange(width, height))
         },
+        //ctrl+z and ctrl+shift+z (cmd on mac)
+        //not while a text box has focus, it undoes its own typing then
+        iced::Event::Keyboard(keyboard::Event::KeyPressed { key_code: keyboard::KeyCode::Z, modifiers }) if modifiers.command() && status == event::Status::Ignored => {
+          if modifiers.shift() {
+            Some(AppMessage::Redo)
+          } else {
+            Some(AppMessage::Undo)
+          }
+        },
         _ => None,
       }
     })
@@ -0,0 +1,397 @@
+use chrono::{ Local, NaiveDate };
+use url::Url;
+
+use crate::storage::Bookmark;
+use crate::tags::is_under;
+
+//the search bar's query language. words are and-ed together, and can be scoped to a field:
+//  tag:rust  -tag:old  site:github.com  title:"exact phrase"  note:foo  link:docs  before:2023-01-01  after:2023-01-01
+//OR (in capitals) and parentheses group words, a - in front of a word or group leaves those bookmarks out.
+//words without a field search whatever the search option pick list is set to. only those fields are special,
+//other words with a colon in them (localhost:3000, mailto:me@example.com, c:\files) are searched for as they are
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+  Any,
+  Title,
+  Link,
+  //the link's host, or any subdomain of it
+  Site,
+  //a whole tag, or one under it (tag:lang finds lang/rust), not part of one
+  Tag,
+  //part of any tag, like the other fields. what words without a field search with the Tags pick list option
+  Tags,
+  Note,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+  //text is lowercased
+  Text(Field, String),
+  //added before the start of that day
+  Before(u64),
+  //added on that day or later
+  After(u64),
+  Not(Box<Query>),
+  And(Vec<Query>),
+  Or(Vec<Query>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Open,
+  Close,
+  Not,
+  Or,
+  //the field name, if there was one, and the text after it
+  Word(Option<String>, String),
+}
+
+const FIELDS: [&str; 7] = ["tag", "site", "title", "link", "note", "before", "after"];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+  let mut tokens: Vec<Token> = Vec::new();
+  let mut chars = input.chars().peekable();
+  while let Some(&char) = chars.peek() {
+    if char.is_whitespace() {
+      chars.next();
+      continue;
+    }
+    if char == '(' || char == ')' {
+      chars.next();
+      tokens.push(if char == '(' { Token::Open } else { Token::Close });
+      continue;
+    }
+    if char == '-' {
+      chars.next();
+      tokens.push(Token::Not);
+      continue;
+    }
+    //a word runs until whitespace or a bracket, quotes keep both in
+    let mut field: Option<String> = None;
+    let mut word: String = String::new();
+    let mut quoted: bool = false;
+    while let Some(&char) = chars.peek() {
+      if char.is_whitespace() || char == '(' || char == ')' {
+        break;
+      }
+      chars.next();
+      if char == '"' {
+        quoted = true;
+        let mut closed: bool = false;
+        while let Some(char) = chars.next() {
+          match char {
+            '"' => {
+              closed = true;
+              break;
+            },
+            '\\' => {
+              if let Some(escaped) = chars.next() {
+                word.push(escaped);
+              }
+            },
+            _ => word.push(char),
+          }
+        }
+        if !closed {
+          return Err("A quote is missing its closing \"".to_string());
+        }
+      } else if char == ':' && field.is_none() && !quoted && FIELDS.contains(&word.to_lowercase().as_str()) {
+        field = Some(word.to_lowercase());
+        word = String::new();
+      } else {
+        word.push(char);
+      }
+    }
+    if field.is_none() && !quoted && word == "OR" {
+      tokens.push(Token::Or);
+    } else {
+      tokens.push(Token::Word(field, word));
+    }
+  }
+  Ok(tokens)
+}
+
+//start of the day, in local time
+fn parse_date(field: &str, value: &str) -> Result<u64, String> {
+  NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)).and_then(|datetime| datetime.and_local_timezone(Local).earliest()).map(|datetime| datetime.timestamp().max(0) as u64).ok_or_else(|| {
+    format!("{}: needs a date like 2023-01-31, not \"{}\"", field, value)
+  })
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  position: usize,
+  default_field: Field,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token: Option<Token> = self.tokens.get(self.position).cloned();
+    self.position += 1;
+    token
+  }
+
+  fn or_expression(&mut self) -> Result<Query, String> {
+    let mut alternatives: Vec<Query> = vec![self.and_expression()?];
+    while self.peek() == Some(&Token::Or) {
+      self.next();
+      if matches!(self.peek(), None | Some(Token::Close) | Some(Token::Or)) {
+        return Err("OR needs something on both sides".to_string());
+      }
+      alternatives.push(self.and_expression()?);
+    }
+    Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Query::Or(alternatives) })
+  }
+
+  fn and_expression(&mut self) -> Result<Query, String> {
+    let mut all: Vec<Query> = Vec::new();
+    while !matches!(self.peek(), None | Some(Token::Close) | Some(Token::Or)) {
+      all.push(self.unary()?);
+    }
+    match all.len() {
+      0 if self.peek() == Some(&Token::Or) => Err("OR needs something on both sides".to_string()),
+      0 => Err("Brackets need something inside them".to_string()),
+      1 => Ok(all.remove(0)),
+      _ => Ok(Query::And(all)),
+    }
+  }
+
+  fn unary(&mut self) -> Result<Query, String> {
+    match self.next() {
+      Some(Token::Not) => {
+        if matches!(self.peek(), None | Some(Token::Close) | Some(Token::Or)) {
+          return Err("- needs a word or brackets right after it".to_string());
+        }
+        Ok(Query::Not(Box::new(self.unary()?)))
+      },
+      Some(Token::Open) => {
+        let inside: Query = self.or_expression()?;
+        match self.next() {
+          Some(Token::Close) => Ok(inside),
+          _ => Err("A bracket is missing its closing )".to_string()),
+        }
+      },
+      Some(Token::Word(field, value)) => self.term(field, value),
+      //and_expression stops before these
+      _ => Err("Unexpected end of search".to_string()),
+    }
+  }
+
+  fn term(&self, field: Option<String>, value: String) -> Result<Query, String> {
+    let Some(field) = field else {
+      return Ok(Query::Text(self.default_field, value.to_lowercase()));
+    };
+    if value.is_empty() {
+      return Err(format!("{}: needs something after it", field));
+    }
+    let value: String = value.to_lowercase();
+    match field.as_str() {
+      "tag" => Ok(Query::Text(Field::Tag, value)),
+      "site" => Ok(Query::Text(Field::Site, value.trim_start_matches("www.").to_string())),
+      "title" => Ok(Query::Text(Field::Title, value)),
+      "link" => Ok(Query::Text(Field::Link, value)),
+      "note" => Ok(Query::Text(Field::Note, value)),
+      "before" => Ok(Query::Before(parse_date(&field, &value)?)),
+      "after" => Ok(Query::After(parse_date(&field, &value)?)),
+      //tokenize only splits off the fields above
+      _ => unreachable!(),
+    }
+  }
+}
+
+//none if there is nothing to search for
+pub fn parse(input: &str, default_field: Field) -> Result<Option<Query>, String> {
+  let tokens: Vec<Token> = tokenize(input)?;
+  if tokens.is_empty() {
+    return Ok(None);
+  }
+  let mut parser: Parser = Parser {
+    tokens,
+    position: 0,
+    default_field,
+  };
+  let query: Query = parser.or_expression()?;
+  if parser.peek().is_some() {
+    return Err("A ) has no opening bracket".to_string());
+  }
+  Ok(Some(query))
+}
+
+fn site_matches(link: &str, site: &str) -> bool {
+  let Some(host) = Url::parse(link).ok().and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_string())) else {
+    return false;
+  };
+  host == site || host.ends_with(&format!(".{}", site))
+}
+
+impl Query {
+  //the words being looked for, leaving out the ones after a -
+  pub fn terms(&self) -> Vec<(Field, &str)> {
+    match self {
+      Query::Text(field, text) => vec![(*field, text.as_str())],
+      Query::And(queries) | Query::Or(queries) => queries.iter().flat_map(Query::terms).collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  //text_matches decides whether a title, link, tag or note has a word in it (contains, or close enough when fuzzy).
+  //whole tags and sites are always exact
+  pub fn matches_with(&self, bookmark: &Bookmark, text_matches: &dyn Fn(&str, &str) -> bool) -> bool {
+    match self {
+      Query::Text(field, text) => {
+        let title = || text_matches(&bookmark.title, text);
+        let link = || text_matches(&bookmark.link, text);
+        let tags = || bookmark.tags.iter().any(|tag| text_matches(tag, text));
+        let note = || bookmark.note.as_ref().map(|note| text_matches(note, text)).unwrap_or(false);
+        match field {
+          Field::Any => title() || link() || tags() || note(),
+          Field::Title => title(),
+          Field::Link => link(),
+          Field::Site => site_matches(&bookmark.link, text),
+          Field::Tag => bookmark.tags.iter().any(|tag| is_under(&tag.to_lowercase(), text)),
+          Field::Tags => tags(),
+          Field::Note => note(),
+        }
+      },
+      Query::Before(timestamp) => bookmark.timestamp < *timestamp,
+      Query::After(timestamp) => bookmark.timestamp >= *timestamp,
+      Query::Not(query) => !query.matches_with(bookmark, text_matches),
+      Query::And(queries) => queries.iter().all(|query| query.matches_with(bookmark, text_matches)),
+      Query::Or(queries) => queries.iter().any(|query| query.matches_with(bookmark, text_matches)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(field: Field, text: &str) -> Query {
+    Query::Text(field, text.to_string())
+  }
+
+  fn parsed(input: &str) -> Query {
+    parse(input, Field::Any).unwrap().unwrap()
+  }
+
+  fn error(input: &str) -> String {
+    parse(input, Field::Any).unwrap_err()
+  }
+
+  fn bookmark(link: &str, tags: &[&str]) -> Bookmark {
+    Bookmark {
+      title: "Title".to_string(),
+      link: link.to_string(),
+      note: None,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      uuid: "a".to_string(),
+      timestamp: 1,
+    }
+  }
+
+  fn contains(text: &str, term: &str) -> bool {
+    text.to_lowercase().contains(term)
+  }
+
+  #[test]
+  fn nothing_to_search_is_none() {
+    assert_eq!(parse("", Field::Any), Ok(None));
+    assert_eq!(parse("   ", Field::Any), Ok(None));
+  }
+
+  #[test]
+  fn words_are_anded_and_lowercased() {
+    assert_eq!(parsed("Rust"), text(Field::Any, "rust"));
+    assert_eq!(parsed("rust  Async"), Query::And(vec![text(Field::Any, "rust"), text(Field::Any, "async")]));
+    assert_eq!(parse("rust", Field::Title), Ok(Some(text(Field::Title, "rust"))));
+  }
+
+  #[test]
+  fn fields_scope_words() {
+    assert_eq!(parsed("tag:Rust"), text(Field::Tag, "rust"));
+    assert_eq!(parsed("TITLE:book"), text(Field::Title, "book"));
+    assert_eq!(parsed("link:docs note:todo"), Query::And(vec![text(Field::Link, "docs"), text(Field::Note, "todo")]));
+    assert_eq!(parsed("site:www.GitHub.com"), text(Field::Site, "github.com"));
+    assert_eq!(parsed("before:2023-01-31"), Query::Before(parse_date("before", "2023-01-31").unwrap()));
+    assert_eq!(parsed("after:2023-01-31"), Query::After(parse_date("after", "2023-01-31").unwrap()));
+  }
+
+  #[test]
+  fn other_colons_are_just_text() {
+    assert_eq!(parsed("localhost:3000"), text(Field::Any, "localhost:3000"));
+    assert_eq!(parsed("mailto:me@example.com"), text(Field::Any, "mailto:me@example.com"));
+    assert_eq!(parsed(r"C:\files"), text(Field::Any, r"c:\files"));
+    assert_eq!(parsed("https://example.com"), text(Field::Any, "https://example.com"));
+    assert_eq!(parsed("link:https://example.com"), text(Field::Link, "https://example.com"));
+  }
+
+  #[test]
+  fn quotes_keep_spaces_and_brackets() {
+    assert_eq!(parsed("title:\"Exact (Phrase)\""), text(Field::Title, "exact (phrase)"));
+    assert_eq!(parsed(r#""say \"hi\"""#), text(Field::Any, "say \"hi\""));
+    assert_eq!(parsed("\"OR\""), text(Field::Any, "or"));
+  }
+
+  #[test]
+  fn or_not_and_brackets() {
+    assert_eq!(parsed("a OR b c"), Query::Or(vec![text(Field::Any, "a"), Query::And(vec![text(Field::Any, "b"), text(Field::Any, "c")])]));
+    assert_eq!(parsed("(a OR b) c"), Query::And(vec![Query::Or(vec![text(Field::Any, "a"), text(Field::Any, "b")]), text(Field::Any, "c")]));
+    assert_eq!(parsed("-tag:old"), Query::Not(Box::new(text(Field::Tag, "old"))));
+    assert_eq!(parsed("-(a b)"), Query::Not(Box::new(Query::And(vec![text(Field::Any, "a"), text(Field::Any, "b")]))));
+    //only capitals are OR
+    assert_eq!(parsed("a or b"), Query::And(vec![text(Field::Any, "a"), text(Field::Any, "or"), text(Field::Any, "b")]));
+  }
+
+  #[test]
+  fn errors_say_what_is_wrong() {
+    assert_eq!(error("title:\"open"), "A quote is missing its closing \"");
+    assert_eq!(error("OR a"), "OR needs something on both sides");
+    assert_eq!(error("a OR"), "OR needs something on both sides");
+    assert_eq!(error("a OR OR b"), "OR needs something on both sides");
+    assert_eq!(error("a -"), "- needs a word or brackets right after it");
+    assert_eq!(error("(a"), "A bracket is missing its closing )");
+    assert_eq!(error("a)"), "A ) has no opening bracket");
+    assert_eq!(error("()"), "Brackets need something inside them");
+    assert_eq!(error("tag:"), "tag: needs something after it");
+    assert_eq!(error("before:yesterday"), "before: needs a date like 2023-01-31, not \"yesterday\"");
+  }
+
+  #[test]
+  fn tag_field_is_whole_tags_and_the_tags_under_them() {
+    let query: Query = parsed("tag:lang");
+    assert!(query.matches_with(&bookmark("https://a.com", &["Lang"]), &contains));
+    assert!(query.matches_with(&bookmark("https://a.com", &["lang/rust"]), &contains));
+    assert!(!query.matches_with(&bookmark("https://a.com", &["language"]), &contains));
+  }
+
+  #[test]
+  fn tags_option_finds_part_of_a_tag() {
+    let query: Query = parse("go", Field::Tags).unwrap().unwrap();
+    assert!(query.matches_with(&bookmark("https://a.com", &["golang"]), &contains));
+    assert!(!query.matches_with(&bookmark("https://go.dev", &["web"]), &contains));
+  }
+
+  #[test]
+  fn site_field_is_the_host_and_its_subdomains() {
+    let query: Query = parsed("site:github.com");
+    assert!(query.matches_with(&bookmark("https://www.github.com/a", &[]), &contains));
+    assert!(query.matches_with(&bookmark("https://gist.github.com/a", &[]), &contains));
+    assert!(!query.matches_with(&bookmark("https://notgithub.com/a", &[]), &contains));
+    assert!(!query.matches_with(&bookmark("https://example.com/github.com", &[]), &contains));
+  }
+
+  #[test]
+  fn dates_and_not() {
+    let day: u64 = parse_date("after", "2023-01-31").unwrap();
+    let mut old: Bookmark = bookmark("https://a.com", &[]);
+    old.timestamp = day - 1;
+    assert!(parsed("before:2023-01-31").matches_with(&old, &contains));
+    assert!(!parsed("after:2023-01-31").matches_with(&old, &contains));
+    assert!(parsed("-after:2023-01-31").matches_with(&old, &contains));
+  }
+}
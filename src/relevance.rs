@@ -0,0 +1,136 @@
+use crate::fuzzy;
+use crate::query::{ Field, Query };
+use crate::storage::{ Bookmark, SECONDS_IN_DAY };
+
+//scoring for the "relevant" sort. every word searched for scores by where it was found (title > tags > link > note),
+//how well it matched (the whole field, a whole word, the start of a word or just somewhere inside), and how often.
+//newer bookmarks get a boost on top, which also orders everything when there is no search.
+//in fuzzy mode a word that is only close to something scores by how close, below any exact match
+
+const TITLE_WEIGHT: f64 = 4.0;
+const TAG_WEIGHT: f64 = 3.0;
+const LINK_WEIGHT: f64 = 2.0;
+const NOTE_WEIGHT: f64 = 1.0;
+
+//a bookmark from today scores up to this much more than a very old one
+const RECENCY_BOOST: f64 = 0.25;
+//the boost halves every this many days
+const RECENCY_HALF_LIFE_DAYS: f64 = 180.0;
+
+//how well term matches text (both lowercased), counting repeats a little. 0 if it isn't there
+fn match_score(text: &str, term: &str, fuzzy_similarity: Option<f64>) -> f64 {
+  if term.is_empty() {
+    return 0.0;
+  }
+  if !text.contains(term) {
+    let similarity: f64 = fuzzy_similarity.map(|_| fuzzy::similarity(text, term)).unwrap_or(0.0);
+    return if similarity >= fuzzy_similarity.unwrap_or(f64::MAX) { similarity } else { 0.0 };
+  }
+  if text == term {
+    return 4.0;
+  }
+  let words: Vec<&str> = text.split(|char: char| !char.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+  let quality: f64 = if words.contains(&term) {
+    3.0
+  } else if words.iter().any(|word| word.starts_with(term)) {
+    2.0
+  } else {
+    1.0
+  };
+  //repeats help, but not enough to beat a better match
+  let count: usize = text.matches(term).count().min(5);
+  quality * (1.0 + 0.25 * (count - 1) as f64)
+}
+
+fn term_score(bookmark: &Bookmark, field: Field, term: &str, fuzzy_similarity: Option<f64>) -> f64 {
+  let title = || TITLE_WEIGHT * match_score(&bookmark.title.to_lowercase(), term, fuzzy_similarity);
+  let tags = || TAG_WEIGHT * bookmark.tags.iter().map(|tag| match_score(&tag.to_lowercase(), term, fuzzy_similarity)).fold(0.0, f64::max);
+  let link = || LINK_WEIGHT * match_score(&bookmark.link.to_lowercase(), term, fuzzy_similarity);
+  let note = || NOTE_WEIGHT * bookmark.note.as_ref().map(|note| match_score(&note.to_lowercase(), term, fuzzy_similarity)).unwrap_or(0.0);
+  match field {
+    Field::Any => title() + tags() + link() + note(),
+    Field::Title => title(),
+    Field::Tag | Field::Tags => tags(),
+    Field::Link | Field::Site => link(),
+    Field::Note => note(),
+  }
+}
+
+//fuzzy_similarity is the lowest similarity that counts as a match in fuzzy mode, none when not searching fuzzily
+pub fn score(query: Option<&Query>, bookmark: &Bookmark, now: u64, fuzzy_similarity: Option<f64>) -> f64 {
+  let terms: f64 = query.map(|query| query.terms().into_iter().map(|(field, term)| term_score(bookmark, field, term, fuzzy_similarity)).sum()).unwrap_or(0.0);
+  let age_days: f64 = now.saturating_sub(bookmark.timestamp) as f64 / SECONDS_IN_DAY as f64;
+  let recency: f64 = RECENCY_BOOST * 0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+  //multiplied so recency only breaks near ties between matches, but still orders bookmarks when nothing is searched
+  (terms + 1.0) * (1.0 + recency)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::query::parse;
+
+  const NOW: u64 = 1_700_000_000;
+
+  fn bookmark(uuid: &str, title: &str, link: &str, tags: &[&str], note: Option<&str>, days_old: u64) -> Bookmark {
+    Bookmark {
+      title: title.to_string(),
+      link: link.to_string(),
+      note: note.map(str::to_string),
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      uuid: uuid.to_string(),
+      timestamp: NOW - days_old * SECONDS_IN_DAY,
+    }
+  }
+
+  //uuids, best first
+  fn ranked(input: &str, bookmarks: &[Bookmark]) -> Vec<String> {
+    let query: Option<Query> = parse(input, Field::Any).unwrap();
+    let mut scored: Vec<(f64, &Bookmark)> = bookmarks.iter().map(|bookmark| (score(query.as_ref(), bookmark, NOW, None), bookmark)).collect();
+    scored.sort_by(|(score1, _), (score2, _)| score2.total_cmp(score1));
+    scored.into_iter().map(|(_, bookmark)| bookmark.uuid.clone()).collect()
+  }
+
+  #[test]
+  fn title_beats_tags_beats_link_beats_note() {
+    let bookmarks: Vec<Bookmark> = vec![
+      bookmark("note", "Reading", "https://example.com/a", &[], Some("all about rust here"), 0),
+      bookmark("link", "Reading", "https://example.com/rust/book", &[], None, 0),
+      bookmark("title", "Learn rust today", "https://example.com/b", &[], None, 0),
+      bookmark("tag", "Reading", "https://example.com/c", &["rust lang"], None, 0),
+    ];
+    assert_eq!(ranked("rust", &bookmarks), vec!["title", "tag", "link", "note"]);
+  }
+
+  #[test]
+  fn closer_matches_rank_higher() {
+    let bookmarks: Vec<Bookmark> = vec![
+      bookmark("inside", "Trust", "https://example.com/a", &[], None, 0),
+      bookmark("start", "Rustacean", "https://example.com/b", &[], None, 0),
+      bookmark("whole field", "Rust", "https://example.com/c", &[], None, 0),
+      bookmark("word", "Rust book", "https://example.com/d", &[], None, 0),
+    ];
+    assert_eq!(ranked("rust", &bookmarks), vec!["whole field", "word", "start", "inside"]);
+  }
+
+  #[test]
+  fn newer_wins_a_tie() {
+    let bookmarks: Vec<Bookmark> = vec![
+      bookmark("old", "Rust book", "https://example.com/a", &[], None, 400),
+      bookmark("new", "Rust book", "https://example.com/b", &[], None, 1),
+      bookmark("middle", "Rust book", "https://example.com/c", &[], None, 100),
+    ];
+    assert_eq!(ranked("rust", &bookmarks), vec!["new", "middle", "old"]);
+    //and orders everything when nothing is searched
+    assert_eq!(ranked("", &bookmarks), vec!["new", "middle", "old"]);
+  }
+
+  #[test]
+  fn recency_does_not_beat_a_better_field() {
+    let bookmarks: Vec<Bookmark> = vec![
+      bookmark("new note", "Reading", "https://example.com/a", &[], Some("rust"), 0),
+      bookmark("old title", "Rust", "https://example.com/b", &[], None, 2000),
+    ];
+    assert_eq!(ranked("rust", &bookmarks), vec!["old title", "new note"]);
+  }
+}
@@ -0,0 +1,107 @@
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, text, text_input, pick_list, scrollable, row, column, Column };
+
+use crate::bookmark_bar::{ Options, SearchOptions, SortOptions };
+use crate::query;
+use crate::storage::{ SavedSearch, Storage };
+use crate::styles;
+
+#[derive(Clone, Debug)]
+pub enum SavedSearchMessage {
+  NameInput(usize, String),
+  QueryInput(usize, String),
+  SearchOptionChange(usize, SearchOptions),
+  SortOptionChange(usize, SortOptions),
+  Delete(usize),
+  Close,
+}
+
+impl SavedSearchMessage {
+  //the text box this was typed into, typing is one undo step per box and saved once it stops
+  pub fn typing_field(message: &SavedSearchMessage) -> Option<String> {
+    match message {
+      SavedSearchMessage::NameInput(index, _) => Some(format!("saved_search_name_{}", index)),
+      SavedSearchMessage::QueryInput(index, _) => Some(format!("saved_search_query_{}", index)),
+      _ => None,
+    }
+  }
+
+  pub fn is_save_after(message: &SavedSearchMessage) -> bool {
+    !matches!(message, SavedSearchMessage::Close)
+  }
+}
+
+pub struct SavedSearchList {}
+
+impl SavedSearchList {
+  pub fn new() -> SavedSearchList {
+    SavedSearchList {}
+  }
+
+  pub fn update(&mut self, message: SavedSearchMessage, storage: &mut Storage) {
+    let mut saved_searches: Vec<SavedSearch> = storage.stored.as_ref().unwrap().saved_searches.clone();
+    match message {
+      SavedSearchMessage::NameInput(index, name) => {
+        if let Some(saved_search) = saved_searches.get_mut(index) {
+          saved_search.name = name;
+        }
+      },
+      SavedSearchMessage::QueryInput(index, query) => {
+        if let Some(saved_search) = saved_searches.get_mut(index) {
+          saved_search.query = query;
+        }
+      },
+      SavedSearchMessage::SearchOptionChange(index, search_option) => {
+        if let Some(saved_search) = saved_searches.get_mut(index) {
+          saved_search.search_option = search_option;
+        }
+      },
+      SavedSearchMessage::SortOptionChange(index, sort_option) => {
+        if let Some(saved_search) = saved_searches.get_mut(index) {
+          saved_search.sort_option = sort_option;
+        }
+      },
+      SavedSearchMessage::Delete(index) => {
+        if index < saved_searches.len() {
+          saved_searches.remove(index);
+        }
+      },
+      SavedSearchMessage::Close => return,
+    }
+    storage.set_saved_searches(saved_searches);
+  }
+
+  pub fn view<'a>(&'a self, storage: &'a Storage, saved_counts: &[usize]) -> Element<'a, SavedSearchMessage> {
+    let saved_searches: &Vec<SavedSearch> = &storage.stored.as_ref().unwrap().saved_searches;
+    let mut search_elements: Vec<Element<SavedSearchMessage>> = Vec::new();
+    for (index, saved_search) in saved_searches.iter().enumerate() {
+      //the query can be edited into something that doesn't parse, show why under it
+      let status: String = match query::parse(&saved_search.query, saved_search.search_option.field()) {
+        Ok(_) => format!("Finds {} bookmarks", saved_counts.get(index).copied().unwrap_or_default()),
+        Err(error) => error,
+      };
+      let search_column: Column<SavedSearchMessage> = column![
+        row![
+          text_input("Name", &saved_search.name).on_input(move |value| SavedSearchMessage::NameInput(index, value)).width(Length::FillPortion(1)),
+          text_input("Search Query", &saved_search.query).on_input(move |value| SavedSearchMessage::QueryInput(index, value)).width(Length::FillPortion(3)),
+          pick_list(SearchOptions::all(), Some(saved_search.search_option), move |value| SavedSearchMessage::SearchOptionChange(index, value)),
+          pick_list(SortOptions::all(), Some(saved_search.sort_option), move |value| SavedSearchMessage::SortOptionChange(index, value)),
+          button("Delete").on_press(SavedSearchMessage::Delete(index)),
+        ].spacing(5).align_items(Alignment::Center),
+        text(status).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      ].spacing(5);
+      search_elements.push(container(search_column).style(theme::Container::Custom(Box::new(styles::BookmarkContainer))).padding(10).into());
+    }
+    if saved_searches.is_empty() {
+      search_elements.push(text("No saved searches, use Save Search next to the search box to add one").into());
+    }
+    column![
+      row![
+        button("Back").on_press(SavedSearchMessage::Close),
+        container(text("Saved Searches").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      scrollable(container(Column::with_children(search_elements).spacing(10)).padding([0, 20, 10, 20])),
+    ].into()
+  }
+}
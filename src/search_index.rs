@@ -0,0 +1,392 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+use std::ops::Bound;
+
+use crate::fuzzy;
+use crate::query::{ Field, Query };
+use crate::storage::Bookmark;
+use crate::tags::is_under;
+
+//an inverted index of the library, so a search only has to look closely at the bookmarks that could match.
+//kept up to date by Storage::apply as bookmarks change, instead of being rebuilt.
+//bookmarks are numbered inside the index so the word lists hold small numbers instead of uuid strings
+
+pub struct SearchIndex {
+  ids: HashMap<String, u32>,
+  //lowercased copies of the bookmarks by id (none once removed), so matching doesn't lowercase the whole library
+  lowercased: Vec<Option<Bookmark>>,
+  //every word in a title, link, tag or note, and the bookmarks it is in
+  words: BTreeMap<String, HashSet<u32>>,
+  //words get numbers too, and every two and three letter piece of a word lists the words it is in (numbers only grow,
+  //so the lists stay sorted). a normal search only checks the words that have all the pieces of the search word
+  word_numbers: HashMap<String, u32>,
+  word_texts: Vec<String>,
+  pieces: HashMap<String, Vec<u32>>,
+  //whole tags, lowercased. sorted, so the tags under one come right after it
+  tags: BTreeMap<String, HashSet<u32>>,
+}
+
+fn bookmark_words(bookmark: &Bookmark) -> HashSet<String> {
+  let mut words: HashSet<String> = HashSet::new();
+  words.extend(fuzzy::tokens(&bookmark.title));
+  words.extend(fuzzy::tokens(&bookmark.link));
+  for tag in &bookmark.tags {
+    words.extend(fuzzy::tokens(tag));
+  }
+  if let Some(note) = &bookmark.note {
+    words.extend(fuzzy::tokens(note));
+  }
+  words
+}
+
+//smallest set first, so there is less to check against the others
+fn intersect(mut sets: Vec<HashSet<u32>>) -> Option<HashSet<u32>> {
+  sets.sort_unstable_by_key(|set| set.len());
+  let mut sets = sets.into_iter();
+  let first: HashSet<u32> = sets.next()?;
+  Some(sets.fold(first, |mut all, set| {
+    all.retain(|id| set.contains(id));
+    all
+  }))
+}
+
+impl SearchIndex {
+  pub fn new() -> SearchIndex {
+    SearchIndex {
+      ids: HashMap::new(),
+      lowercased: Vec::new(),
+      words: BTreeMap::new(),
+      word_numbers: HashMap::new(),
+      word_texts: Vec::new(),
+      pieces: HashMap::new(),
+      tags: BTreeMap::new(),
+    }
+  }
+
+  pub fn build<'a>(bookmarks: impl Iterator<Item = &'a Bookmark>) -> SearchIndex {
+    let mut index: SearchIndex = SearchIndex::new();
+    for bookmark in bookmarks {
+      index.insert(bookmark);
+    }
+    index
+  }
+
+  //adding a bookmark that is already in the index updates it
+  pub fn insert(&mut self, bookmark: &Bookmark) {
+    self.remove(&bookmark.uuid);
+    let id: u32 = match self.ids.get(&bookmark.uuid) {
+      Some(id) => *id,
+      None => {
+        let id: u32 = self.lowercased.len() as u32;
+        self.ids.insert(bookmark.uuid.clone(), id);
+        self.lowercased.push(None);
+        id
+      },
+    };
+    for word in bookmark_words(bookmark) {
+      self.number_word(&word);
+      self.words.entry(word).or_default().insert(id);
+    }
+    let lowercased: Bookmark = Bookmark {
+      title: bookmark.title.to_lowercase(),
+      link: bookmark.link.to_lowercase(),
+      note: bookmark.note.as_ref().map(|note| note.to_lowercase()),
+      tags: bookmark.tags.iter().map(|tag| tag.to_lowercase()).collect(),
+      ..bookmark.clone()
+    };
+    for tag in &lowercased.tags {
+      self.tags.entry(tag.clone()).or_default().insert(id);
+    }
+    self.lowercased[id as usize] = Some(lowercased);
+  }
+
+  //a word keeps its number after its last bookmark is gone, like ids do
+  fn number_word(&mut self, word: &str) {
+    if self.word_numbers.contains_key(word) {
+      return;
+    }
+    let number: u32 = self.word_texts.len() as u32;
+    let chars: Vec<char> = word.chars().collect();
+    let pieces: HashSet<String> = (2..=3).flat_map(|length| chars.windows(length).map(|piece| piece.iter().collect::<String>())).collect();
+    for piece in pieces {
+      self.pieces.entry(piece).or_default().push(number);
+    }
+    self.word_numbers.insert(word.to_string(), number);
+    self.word_texts.push(word.to_string());
+  }
+
+  //the id stays with the uuid, in case it is added back (undo)
+  pub fn remove(&mut self, uuid: &str) {
+    let Some(&id) = self.ids.get(uuid) else {
+      return;
+    };
+    let Some(lowercased) = self.lowercased[id as usize].take() else {
+      return;
+    };
+    for word in bookmark_words(&lowercased) {
+      if let Some(ids) = self.words.get_mut(&word) {
+        ids.remove(&id);
+        if ids.is_empty() {
+          self.words.remove(&word);
+        }
+      }
+    }
+    for tag in &lowercased.tags {
+      if let Some(ids) = self.tags.get_mut(tag) {
+        ids.remove(&id);
+        if ids.is_empty() {
+          self.tags.remove(tag);
+        }
+      }
+    }
+  }
+
+  //every tag in the library, lowercased
+  pub fn tag_names(&self) -> impl Iterator<Item = &String> {
+    self.tags.keys()
+  }
+
+  //words of the library with the search word in them
+  fn containing_words(&self, term_word: &str) -> Vec<&String> {
+    let chars: Vec<char> = term_word.chars().collect();
+    //single letters aren't split into pieces, there are few enough words to just look through them
+    if chars.len() < 2 {
+      return self.words.keys().filter(|word| word.contains(term_word)).collect();
+    }
+    let piece_lists: Option<Vec<&Vec<u32>>> = chars.windows(chars.len().min(3)).map(|piece| self.pieces.get(&piece.iter().collect::<String>())).collect();
+    let Some(mut piece_lists) = piece_lists else {
+      return Vec::new();
+    };
+    piece_lists.sort_unstable_by_key(|numbers| numbers.len());
+    let (shortest, others) = piece_lists.split_first().unwrap();
+    shortest.iter().filter(|number| others.iter().all(|numbers| numbers.binary_search(number).is_ok()))
+      .map(|&number| &self.word_texts[number as usize])
+      .filter(|word| word.contains(term_word))
+      .filter_map(|word| self.words.get_key_value(word).map(|(word, _)| word))
+      .collect()
+  }
+
+  //words of the library close enough to the search word for a fuzzy search, all of it or its start (see fuzzy::similarity).
+  //the words are sorted, so the edit distance rows for the start two words share are only worked out once,
+  //and once a start is too different (or already close enough) every word beginning with it is skipped over (or taken) at once
+  fn close_words(&self, term_word: &str, fuzzy_similarity: f64) -> Vec<&String> {
+    let term: Vec<char> = term_word.chars().collect();
+    let term_length: usize = term.len();
+    //a little extra so rounding can't leave out a word right at the limit, fuzzy::similarity has the final say
+    let max_edits: usize = ((1.0 - fuzzy_similarity) * term_length as f64 + 1e-9) as usize;
+    //rows[j] is the distance from the start of the word that is j letters long to every start of the search word
+    let mut rows: Vec<Vec<usize>> = vec![(0..=term_length).collect()];
+    let mut row_chars: Vec<char> = Vec::new();
+    let mut chars: Vec<char> = Vec::new();
+    let mut close: Vec<&String> = Vec::new();
+    let mut words = self.words.range::<String, _>(..);
+    while let Some((word, _)) = words.next() {
+      chars.clear();
+      chars.extend(word.chars());
+      let shared: usize = row_chars.iter().zip(&chars).take_while(|(char1, char2)| char1 == char2).count();
+      rows.truncate(shared + 1);
+      row_chars.truncate(shared);
+      let mut decided: Option<bool> = None;
+      for j in shared + 1..=chars.len() {
+        let mut row: Vec<usize> = vec![j; term_length + 1];
+        for i in 1..=term_length {
+          let cost: usize = if chars[j - 1] == term[i - 1] { 0 } else { 1 };
+          row[i] = (rows[j - 1][i] + 1).min(row[i - 1] + 1).min(rows[j - 1][i - 1] + cost);
+          if i > 1 && j > 1 && chars[j - 1] == term[i - 2] && chars[j - 2] == term[i - 1] {
+            row[i] = row[i].min(rows[j - 2][i - 2] + 1);
+          }
+        }
+        //every later row is at least one more than the smaller of these two, so nothing longer can get close again
+        let too_far: bool = row.iter().min().unwrap() > &max_edits && rows[j - 1].iter().min().unwrap() >= &max_edits;
+        rows.push(row);
+        row_chars.push(chars[j - 1]);
+        if j == term_length && rows[j][term_length] <= max_edits {
+          decided = Some(true);
+        } else if too_far {
+          decided = Some(false);
+        }
+        if decided.is_some() {
+          break;
+        }
+      }
+      match decided {
+        Some(is_close) => {
+          //words are alphanumeric, so nothing starting with this comes after it with the last char added
+          let start: String = row_chars.iter().collect();
+          let after: String = format!("{}{}", start, char::MAX);
+          if is_close {
+            close.extend(self.words.range(start..after.clone()).map(|(word, _)| word));
+          }
+          words = self.words.range((Bound::Excluded(after), Bound::Unbounded));
+        },
+        None if rows[chars.len()][term_length] <= max_edits => close.push(word),
+        None => {},
+      }
+    }
+    close.retain(|word| fuzzy::similarity(word, term_word) >= fuzzy_similarity);
+    close
+  }
+
+  //bookmarks with a word that matches this word of a search. contains for normal searches, close enough for fuzzy ones
+  fn word_postings(&self, term_word: &str, fuzzy_similarity: Option<f64>) -> HashSet<u32> {
+    let mut words: Vec<&String> = self.containing_words(term_word);
+    if let Some(fuzzy_similarity) = fuzzy_similarity {
+      words.extend(self.close_words(term_word, fuzzy_similarity));
+    }
+    let mut ids: HashSet<u32> = HashSet::new();
+    for word in words {
+      ids.extend(&self.words[word]);
+    }
+    ids
+  }
+
+  //every bookmark that could match, or none if the query can't narrow it down (only leaving things out, dates)
+  fn candidates(&self, query: &Query, fuzzy_similarity: Option<f64>) -> Option<HashSet<u32>> {
+    match query {
+      Query::Text(Field::Tag, tag) => {
+        let mut ids: HashSet<u32> = HashSet::new();
+        for (_, tag_ids) in self.tags.range(tag.clone()..).take_while(|(name, _)| name.starts_with(tag.as_str())).filter(|(name, _)| is_under(name, tag)) {
+          ids.extend(tag_ids);
+        }
+        Some(ids)
+      },
+      Query::Text(field, text) => {
+        //sites are matched exactly, only the rest can be fuzzy
+        let fuzzy_similarity: Option<f64> = if *field == Field::Site { None } else { fuzzy_similarity };
+        intersect(fuzzy::tokens(text).iter().map(|term_word| self.word_postings(term_word, fuzzy_similarity)).collect())
+      },
+      Query::And(queries) => intersect(queries.iter().filter_map(|query| self.candidates(query, fuzzy_similarity)).collect()),
+      Query::Or(queries) => {
+        let mut ids: HashSet<u32> = HashSet::new();
+        for query in queries {
+          ids.extend(self.candidates(query, fuzzy_similarity)?);
+        }
+        Some(ids)
+      },
+      Query::Not(_) | Query::Before(_) | Query::After(_) => None,
+    }
+  }
+
+  //the uuids of the bookmarks matching the query, in no particular order
+  pub fn search(&self, query: Option<&Query>, fuzzy_similarity: Option<f64>) -> Vec<String> {
+    let text_matches = |text: &str, term: &str| match fuzzy_similarity {
+      Some(fuzzy_similarity) => fuzzy::similarity(text, term) >= fuzzy_similarity,
+      None => text.contains(term),
+    };
+    let matching = |bookmark: &&Bookmark| query.map(|query| query.matches_with(bookmark, &text_matches)).unwrap_or(true);
+    match query.and_then(|query| self.candidates(query, fuzzy_similarity)) {
+      Some(candidates) => candidates.into_iter().filter_map(|id| self.lowercased[id as usize].as_ref()).filter(matching).map(|bookmark| bookmark.uuid.clone()).collect(),
+      None => self.lowercased.iter().flatten().filter(matching).map(|bookmark| bookmark.uuid.clone()).collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::query::parse;
+
+  const SYLLABLES: [&str; 16] = ["ka", "ru", "st", "lang", "é", "go", "pro", "gram", "mé", "ing", "xo", "ba", "ñu", "tion", "re", "t"];
+
+  //the same made up library every time, with words that share starts and pieces so the index has something to skip
+  fn library() -> Vec<Bookmark> {
+    let mut seed: u64 = 12345;
+    let mut random = move |below: usize| {
+      seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      (seed >> 33) as usize % below
+    };
+    let mut word = move || (0..1 + random(4)).map(|_| SYLLABLES[random(SYLLABLES.len())]).collect::<String>();
+    (0..400).map(|number| Bookmark {
+      title: format!("{} {} {} {}", word(), word(), word(), number),
+      link: format!("https://{}.com/{}", word(), word()),
+      note: if number % 3 == 0 { Some(word()) } else { None },
+      tags: vec![word(), format!("{}/{}", word(), word())],
+      uuid: format!("uuid{}", number),
+      timestamp: number,
+    }).collect()
+  }
+
+  //what search should find, by checking every bookmark
+  fn every_match(bookmarks: &[Bookmark], query: &Query, fuzzy_similarity: Option<f64>) -> Vec<String> {
+    let text_matches = |text: &str, term: &str| match fuzzy_similarity {
+      Some(fuzzy_similarity) => fuzzy::similarity(text, term) >= fuzzy_similarity,
+      None => text.contains(term),
+    };
+    let mut uuids: Vec<String> = bookmarks.iter().filter(|bookmark| {
+      let lowercased: Bookmark = Bookmark {
+        title: bookmark.title.to_lowercase(),
+        link: bookmark.link.to_lowercase(),
+        note: bookmark.note.as_ref().map(|note| note.to_lowercase()),
+        tags: bookmark.tags.iter().map(|tag| tag.to_lowercase()).collect(),
+        ..(*bookmark).clone()
+      };
+      query.matches_with(&lowercased, &text_matches)
+    }).map(|bookmark| bookmark.uuid.clone()).collect();
+    uuids.sort();
+    uuids
+  }
+
+  fn assert_same_as_every_match(index: &SearchIndex, bookmarks: &[Bookmark], input: &str, field: Field, fuzzy_similarity: Option<f64>) {
+    let query: Query = parse(input, field).unwrap().unwrap();
+    let mut found: Vec<String> = index.search(Some(&query), fuzzy_similarity);
+    found.sort();
+    assert_eq!(found, every_match(bookmarks, &query, fuzzy_similarity), "{} {:?}", input, fuzzy_similarity);
+  }
+
+  const SEARCHES: [&str; 16] = ["k", "ka", "rus", "langing", "mé", "gramtion", "progrma", "ñu", "actoin", "zzz", "lang pro", "tag:ka", "title:go -re", "go OR xo", "site:kago.com", "after:1970-01-02 ru"];
+
+  #[test]
+  fn finds_the_same_as_checking_every_bookmark() {
+    let bookmarks: Vec<Bookmark> = library();
+    let index: SearchIndex = SearchIndex::build(bookmarks.iter());
+    for fuzzy_similarity in [None, Some(0.5), Some(0.7), Some(0.8)] {
+      for input in SEARCHES {
+        assert_same_as_every_match(&index, &bookmarks, input, Field::Any, fuzzy_similarity);
+      }
+      assert_same_as_every_match(&index, &bookmarks, "go", Field::Tags, fuzzy_similarity);
+      assert_same_as_every_match(&index, &bookmarks, "gramt", Field::Title, fuzzy_similarity);
+    }
+  }
+
+  #[test]
+  fn stays_up_to_date_as_bookmarks_change() {
+    let mut bookmarks: Vec<Bookmark> = library();
+    let mut index: SearchIndex = SearchIndex::build(bookmarks.iter());
+    for bookmark in bookmarks.iter_mut().take(50) {
+      bookmark.title = "renamed zzz".to_string();
+      bookmark.tags = vec!["Moved/Here".to_string()];
+      index.insert(bookmark);
+    }
+    for bookmark in bookmarks.drain(50..100) {
+      index.remove(&bookmark.uuid);
+    }
+    for fuzzy_similarity in [None, Some(0.7)] {
+      for input in SEARCHES.iter().chain(&["zzz", "renamed", "tag:moved"]) {
+        assert_same_as_every_match(&index, &bookmarks, input, Field::Any, fuzzy_similarity);
+      }
+    }
+  }
+
+  #[test]
+  fn no_query_is_everything() {
+    let bookmarks: Vec<Bookmark> = library();
+    let index: SearchIndex = SearchIndex::build(bookmarks.iter());
+    assert_eq!(index.search(None, None).len(), bookmarks.len());
+  }
+
+  #[test]
+  fn tags_are_found_with_the_ones_under_them() {
+    let bookmarks: Vec<Bookmark> = vec![
+      Bookmark::new("a".to_string(), "https://a.com".to_string(), None, vec!["Lang".to_string()], Some(1)),
+      Bookmark::new("b".to_string(), "https://b.com".to_string(), None, vec!["lang/rust/async".to_string()], Some(1)),
+      Bookmark::new("c".to_string(), "https://c.com".to_string(), None, vec!["language".to_string(), "lang-x".to_string()], Some(1)),
+    ];
+    let index: SearchIndex = SearchIndex::build(bookmarks.iter());
+    let mut found: Vec<String> = index.search(Some(&parse("tag:lang", Field::Any).unwrap().unwrap()), None);
+    found.sort();
+    let mut expected: Vec<String> = vec![bookmarks[0].uuid.clone(), bookmarks[1].uuid.clone()];
+    expected.sort();
+    assert_eq!(found, expected);
+    let names: Vec<&String> = index.tag_names().collect();
+    assert_eq!(names, vec!["lang", "lang-x", "lang/rust/async", "language"]);
+  }
+}
@@ -0,0 +1,193 @@
+use iced::Element;
+use iced::{ alignment, Alignment, Length };
+use iced::widget::{ button, checkbox, container, text, text_input, scrollable, row, column };
+
+use crate::storage::{ Settings, Storage, StorageError };
+use crate::styles;
+
+#[derive(Clone, Debug)]
+pub enum SettingsMessage {
+  MigrateToSqlite,
+  MigrateDone(Result<(), StorageError>),
+  TrashDaysInput(String),
+  TrackingParametersInput(String),
+  AllowedSchemesInput(String),
+  FuzzySimilarityInput(String),
+  LowercaseTagsToggle(bool),
+  TagCharactersInput(String),
+  Close,
+}
+
+impl SettingsMessage {
+  //the text box this was typed into, typing is one undo step per box and saved once it stops
+  pub fn typing_field(message: &SettingsMessage) -> Option<String> {
+    match message {
+      SettingsMessage::TrashDaysInput(_) => Some("trash_days".to_string()),
+      SettingsMessage::TrackingParametersInput(_) => Some("tracking_parameters".to_string()),
+      SettingsMessage::AllowedSchemesInput(_) => Some("allowed_schemes".to_string()),
+      SettingsMessage::FuzzySimilarityInput(_) => Some("fuzzy_similarity".to_string()),
+      SettingsMessage::TagCharactersInput(_) => Some("tag_characters".to_string()),
+      _ => None,
+    }
+  }
+
+  pub fn is_save_after(message: &SettingsMessage) -> bool {
+    matches!(message, SettingsMessage::TrashDaysInput(_) | SettingsMessage::TrackingParametersInput(_) | SettingsMessage::AllowedSchemesInput(_) | SettingsMessage::FuzzySimilarityInput(_) | SettingsMessage::LowercaseTagsToggle(_) | SettingsMessage::TagCharactersInput(_))
+  }
+}
+
+pub struct SettingsView {
+  migrating: bool,
+  status: Option<String>,
+  //what is typed in the trash days box, which may not be a number (yet)
+  trash_days_input: Option<String>,
+  //kept as typed, so a trailing comma doesn't disappear mid typing
+  tracking_parameters_input: Option<String>,
+  allowed_schemes_input: Option<String>,
+  fuzzy_similarity_input: Option<String>,
+}
+
+impl SettingsView {
+  pub fn new() -> SettingsView {
+    SettingsView {
+      migrating: false,
+      status: None,
+      trash_days_input: None,
+      tracking_parameters_input: None,
+      allowed_schemes_input: None,
+      fuzzy_similarity_input: None,
+    }
+  }
+
+  pub fn update(&mut self, message: SettingsMessage, storage: &mut Storage) {
+    match message {
+      SettingsMessage::MigrateToSqlite => {
+        self.migrating = true;
+        self.status = Some("Moving the library to SQLite...".to_string());
+      },
+      SettingsMessage::MigrateDone(result) => {
+        self.migrating = false;
+        self.status = Some(match result {
+          Ok(()) => "Moved to SQLite, the old stored.json is in the backups folder".to_string(),
+          Err(error) => error.to_string(),
+        });
+      },
+      SettingsMessage::TrashDaysInput(input) => {
+        if let Ok(trash_days) = input.trim().parse::<u64>() {
+          if trash_days > 0 {
+            let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+            settings.trash_days = trash_days;
+            storage.set_settings(settings);
+          }
+        }
+        self.trash_days_input = Some(input);
+      },
+      SettingsMessage::TrackingParametersInput(input) => {
+        let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+        settings.tracking_parameters = input.split(',').map(|parameter| parameter.trim().to_string()).filter(|parameter| !parameter.is_empty()).collect();
+        storage.set_settings(settings);
+        self.tracking_parameters_input = Some(input);
+      },
+      SettingsMessage::AllowedSchemesInput(input) => {
+        let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+        settings.allowed_schemes = input.split(',').map(|scheme| scheme.trim().trim_end_matches(':').to_lowercase()).filter(|scheme| !scheme.is_empty()).collect();
+        storage.set_settings(settings);
+        self.allowed_schemes_input = Some(input);
+      },
+      SettingsMessage::FuzzySimilarityInput(input) => {
+        if let Ok(fuzzy_similarity) = input.trim().trim_end_matches('%').parse::<u64>() {
+          if fuzzy_similarity > 0 && fuzzy_similarity <= 100 {
+            let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+            settings.fuzzy_similarity = fuzzy_similarity;
+            storage.set_settings(settings);
+          }
+        }
+        self.fuzzy_similarity_input = Some(input);
+      },
+      SettingsMessage::LowercaseTagsToggle(lowercase_tags) => {
+        let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+        settings.lowercase_tags = lowercase_tags;
+        storage.set_settings(settings);
+      },
+      SettingsMessage::TagCharactersInput(input) => {
+        //letters, numbers and spaces are always allowed, commas split tags
+        let mut settings: Settings = storage.stored.as_ref().unwrap().settings.clone();
+        settings.tag_characters = input.chars().filter(|char| !(char.is_alphanumeric() || char.is_whitespace() || *char == ',')).collect();
+        storage.set_settings(settings);
+      },
+      SettingsMessage::Close => {
+        self.trash_days_input = None;
+        self.tracking_parameters_input = None;
+        self.allowed_schemes_input = None;
+        self.fuzzy_similarity_input = None;
+      },
+    }
+  }
+
+  pub fn view(&self, storage: &Storage) -> Element<'_, SettingsMessage> {
+    let mut storage_row = row![
+      text(format!("Library is stored in a {}", storage.describe_backend())).width(Length::Fill),
+    ].spacing(5).align_items(Alignment::Center);
+    if !storage.is_sqlite() {
+      let mut migrate_button = button("Move to SQLite");
+      if !self.migrating {
+        migrate_button = migrate_button.on_press(SettingsMessage::MigrateToSqlite);
+      }
+      storage_row = storage_row.push(migrate_button);
+    }
+    let mut settings = column![
+      text("Storage").size(20),
+      storage_row,
+      text("SQLite only writes the bookmarks that changed, which is much faster for big libraries. This can't be undone from here.").style(styles::BOOKMARK_TIMESTAMP_STYLE),
+    ].spacing(8);
+    if let Some(status) = &self.status {
+      settings = settings.push(text(status));
+    }
+    let trash_days: String = self.trash_days_input.clone().unwrap_or_else(|| storage.stored.as_ref().unwrap().settings.trash_days.to_string());
+    settings = settings.push(text("Trash").size(20)).push(
+      row![
+        text("Permanently delete bookmarks after they have been in the trash for").width(Length::Fill),
+        text_input("30", &trash_days).on_input(SettingsMessage::TrashDaysInput).width(Length::Fixed(60.0)),
+        text("days"),
+      ].spacing(5).align_items(Alignment::Center)
+    );
+    let tracking_parameters: String = self.tracking_parameters_input.clone().unwrap_or_else(|| storage.stored.as_ref().unwrap().settings.tracking_parameters.join(", "));
+    settings = settings.push(text("Links").size(20)).push(
+      text_input("utm_*, fbclid", &tracking_parameters).on_input(SettingsMessage::TrackingParametersInput)
+    ).push(
+      text("Tracking parameters, separated by commas, are ignored when checking for duplicate links. A * at the end matches any parameter starting with the rest.").style(styles::BOOKMARK_TIMESTAMP_STYLE)
+    );
+    let allowed_schemes: String = self.allowed_schemes_input.clone().unwrap_or_else(|| storage.stored.as_ref().unwrap().settings.allowed_schemes.join(", "));
+    settings = settings.push(
+      text_input("https, http", &allowed_schemes).on_input(SettingsMessage::AllowedSchemesInput)
+    ).push(
+      text("Only links starting with one of these schemes, separated by commas, can be opened. Other schemes (like obsidian or zoommtg) start programs, so only add ones you trust.").style(styles::BOOKMARK_TIMESTAMP_STYLE)
+    );
+    let fuzzy_similarity: String = self.fuzzy_similarity_input.clone().unwrap_or_else(|| storage.stored.as_ref().unwrap().settings.fuzzy_similarity.to_string());
+    settings = settings.push(text("Search").size(20)).push(
+      row![
+        text("In fuzzy search, words have to be at least this similar to match").width(Length::Fill),
+        text_input("70", &fuzzy_similarity).on_input(SettingsMessage::FuzzySimilarityInput).width(Length::Fixed(60.0)),
+        text("%"),
+      ].spacing(5).align_items(Alignment::Center)
+    ).push(
+      text("100% only matches exact spellings. Lower lets more typos through, and more unrelated bookmarks with them.").style(styles::BOOKMARK_TIMESTAMP_STYLE)
+    );
+    let stored_settings: &Settings = &storage.stored.as_ref().unwrap().settings;
+    settings = settings.push(text("Tags").size(20)).push(
+      checkbox("Lowercase tags when adding or editing bookmarks", stored_settings.lowercase_tags, SettingsMessage::LowercaseTagsToggle)
+    ).push(
+      row![
+        text("Besides letters, numbers and spaces, tags can have").width(Length::Fill),
+        text_input("-_./", &stored_settings.tag_characters).on_input(SettingsMessage::TagCharactersInput).width(Length::Fixed(120.0)),
+      ].spacing(5).align_items(Alignment::Center)
+    );
+    column![
+      row![
+        button("Back").on_press(SettingsMessage::Close),
+        container(text("Settings").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      scrollable(container(settings).padding([0, 20, 10, 20])),
+    ].into()
+  }
+}
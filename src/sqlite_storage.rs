@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::fs::{ create_dir_all, rename };
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+
+use rusqlite::{ params, Connection, OptionalExtension, ToSql };
+use serde_json::Value;
+
+use crate::storage::{ migrate, Bookmark, BookmarkFilter, SavedSearch, Settings, Storage, StorageBackend, StorageError, Stored, TrashedBookmark, BACKUP_INTERVAL, SCHEMA_VERSION };
+use crate::json_storage::JsonStorage;
+use crate::utils::get_timestamp;
+
+//bookmarks in a sqlite database, so an edit only touches the rows of that bookmark instead of the whole library
+pub struct SqliteStorage {
+  path: PathBuf,
+  //opened on first use, so errors show up on the recovery screen instead of at startup
+  connection: Mutex<Option<Connection>>,
+}
+
+impl SqliteStorage {
+  pub fn new() -> SqliteStorage {
+    SqliteStorage::at(SqliteStorage::path())
+  }
+
+  //a database somewhere other than the data dir
+  pub fn at(path: PathBuf) -> SqliteStorage {
+    SqliteStorage {
+      path,
+      connection: Mutex::new(None),
+    }
+  }
+
+  pub fn path() -> PathBuf {
+    Storage::data_dir().join("stored.sqlite")
+  }
+
+  fn open(path: &Path) -> Result<Connection, rusqlite::Error> {
+    let connection: Connection = Connection::open(path)?;
+    connection.execute_batch("
+      CREATE TABLE IF NOT EXISTS bookmarks (
+        uuid TEXT PRIMARY KEY NOT NULL,
+        title TEXT NOT NULL,
+        link TEXT NOT NULL,
+        note TEXT,
+        timestamp INTEGER NOT NULL
+      );
+      CREATE INDEX IF NOT EXISTS bookmarks_title ON bookmarks (title);
+      CREATE INDEX IF NOT EXISTS bookmarks_link ON bookmarks (link);
+      CREATE INDEX IF NOT EXISTS bookmarks_timestamp ON bookmarks (timestamp);
+      CREATE TABLE IF NOT EXISTS tags (
+        uuid TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (uuid, position)
+      );
+      CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+      CREATE TABLE IF NOT EXISTS trash (
+        uuid TEXT PRIMARY KEY NOT NULL,
+        deleted INTEGER NOT NULL,
+        bookmark TEXT NOT NULL
+      );
+      CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+      );
+    ")?;
+    Ok(connection)
+  }
+
+  //run something against the database, inside a transaction if it writes. the transaction is ended by commit(),
+  //or rolled back as soon as anything in it fails, so a later commit can't save half a batch (Storage writes it all again)
+  fn with_connection<T>(&self, write: bool, action: impl FnOnce(&Connection) -> Result<T, rusqlite::Error>) -> Result<T, StorageError> {
+    let mut connection = self.connection.lock().unwrap();
+    if connection.is_none() {
+      if let Some(dir) = self.path.parent() {
+        create_dir_all(dir).map_err(|_| StorageError::CreateError)?;
+      }
+      *connection = Some(SqliteStorage::open(&self.path).map_err(|error| StorageError::DatabaseError(error.to_string()))?);
+    }
+    let connection: &Connection = connection.as_ref().unwrap();
+    if write && connection.is_autocommit() {
+      connection.execute_batch("BEGIN").map_err(|error| StorageError::DatabaseError(error.to_string()))?;
+    }
+    action(connection).map_err(|error| {
+      if !connection.is_autocommit() {
+        if let Err(rollback_error) = connection.execute_batch("ROLLBACK") {
+          println!("{:?}", rollback_error);
+        }
+      }
+      StorageError::DatabaseError(error.to_string())
+    })
+  }
+
+  fn get_meta(connection: &Connection, key: &str) -> Result<Option<String>, rusqlite::Error> {
+    connection.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0)).optional()
+  }
+
+  fn set_meta(connection: &Connection, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+    connection.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+    Ok(())
+  }
+
+  fn upsert(connection: &Connection, bookmark: &Bookmark) -> Result<(), rusqlite::Error> {
+    connection.execute(
+      "INSERT OR REPLACE INTO bookmarks (uuid, title, link, note, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
+      params![bookmark.uuid, bookmark.title, bookmark.link, bookmark.note, bookmark.timestamp as i64],
+    )?;
+    connection.execute("DELETE FROM tags WHERE uuid = ?1", params![bookmark.uuid])?;
+    for (position, tag) in bookmark.tags.iter().enumerate() {
+      connection.execute("INSERT INTO tags (uuid, position, tag) VALUES (?1, ?2, ?3)", params![bookmark.uuid, position as i64, tag])?;
+    }
+    Ok(())
+  }
+
+  //trashed bookmarks are never searched, so they are kept as json instead of getting their own columns
+  fn upsert_trashed(connection: &Connection, trashed: &TrashedBookmark) -> Result<(), rusqlite::Error> {
+    connection.execute(
+      "INSERT OR REPLACE INTO trash (uuid, deleted, bookmark) VALUES (?1, ?2, ?3)",
+      params![trashed.bookmark.uuid, trashed.deleted as i64, serde_json::to_string(&trashed.bookmark).unwrap()],
+    )?;
+    Ok(())
+  }
+
+  fn read_trash(connection: &Connection) -> Result<HashMap<String, TrashedBookmark>, rusqlite::Error> {
+    let mut trash: HashMap<String, TrashedBookmark> = HashMap::new();
+    let mut statement = connection.prepare("SELECT deleted, bookmark FROM trash")?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?)))?;
+    for row in rows {
+      let (deleted, bookmark) = row?;
+      //a row that doesn't parse is skipped rather than making the whole library unreadable
+      if let Ok(bookmark) = serde_json::from_str::<Bookmark>(&bookmark) {
+        trash.insert(bookmark.uuid.clone(), TrashedBookmark { bookmark, deleted });
+      }
+    }
+    Ok(trash)
+  }
+
+  //bookmarks matching a where clause, with their tags
+  fn read_bookmarks(connection: &Connection, filter: &str, filter_params: &[&dyn rusqlite::ToSql]) -> Result<HashMap<String, Bookmark>, rusqlite::Error> {
+    let mut bookmarks: HashMap<String, Bookmark> = HashMap::new();
+    let mut statement = connection.prepare(&format!("SELECT uuid, title, link, note, timestamp FROM bookmarks WHERE {}", filter))?;
+    let rows = statement.query_map(filter_params, |row| {
+      Ok(Bookmark {
+        uuid: row.get(0)?,
+        title: row.get(1)?,
+        link: row.get(2)?,
+        note: row.get(3)?,
+        tags: Vec::new(),
+        timestamp: row.get::<_, i64>(4)? as u64,
+      })
+    })?;
+    for bookmark in rows {
+      let bookmark: Bookmark = bookmark?;
+      bookmarks.insert(bookmark.uuid.clone(), bookmark);
+    }
+    let mut statement = connection.prepare(&format!("SELECT uuid, tag FROM tags WHERE uuid IN (SELECT uuid FROM bookmarks WHERE {}) ORDER BY uuid, position", filter))?;
+    let rows = statement.query_map(filter_params, |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+      let (uuid, tag) = row?;
+      if let Some(bookmark) = bookmarks.get_mut(&uuid) {
+        bookmark.tags.push(tag);
+      }
+    }
+    Ok(bookmarks)
+  }
+
+  fn read_all(connection: &Connection) -> Result<Stored, rusqlite::Error> {
+    let schema_version: u32 = SqliteStorage::get_meta(connection, "schema_version")?.and_then(|version| version.parse().ok()).unwrap_or(SCHEMA_VERSION);
+    Ok(Stored {
+      schema_version,
+      bookmarks: SqliteStorage::read_bookmarks(connection, "1", &[])?,
+      trash: SqliteStorage::read_trash(connection)?,
+      settings: SqliteStorage::get_meta(connection, "settings")?.and_then(|settings| serde_json::from_str(&settings).ok()).unwrap_or_default(),
+      saved_searches: SqliteStorage::get_meta(connection, "saved_searches")?.and_then(|saved_searches| serde_json::from_str(&saved_searches).ok()).unwrap_or_default(),
+    })
+  }
+
+}
+
+impl StorageBackend for SqliteStorage {
+  fn load(&self) -> Result<Stored, StorageError> {
+    let stored: Stored = self.with_connection(false, SqliteStorage::read_all)?;
+    if stored.schema_version > SCHEMA_VERSION {
+      return Err(StorageError::VersionError);
+    }
+    if stored.schema_version < SCHEMA_VERSION {
+      //same migrations as the json file, so go through a Value
+      JsonStorage::write_backup(&stored, &format!("pre_migration_v{}", stored.schema_version))?;
+      let mut value: Value = serde_json::to_value(&stored).unwrap();
+      migrate(&mut value, stored.schema_version);
+      let stored: Stored = serde_json::from_value(value).map_err(StorageError::from_json)?;
+      self.replace(&stored)?;
+      self.commit()?;
+      return Ok(stored);
+    }
+    Ok(stored)
+  }
+
+  fn upsert_bookmark(&self, bookmark: &Bookmark) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| SqliteStorage::upsert(connection, bookmark))
+  }
+
+  fn delete_bookmark(&self, uuid: &str) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| {
+      connection.execute("DELETE FROM tags WHERE uuid = ?1", params![uuid])?;
+      connection.execute("DELETE FROM bookmarks WHERE uuid = ?1", params![uuid])?;
+      Ok(())
+    })
+  }
+
+  fn upsert_trashed(&self, trashed: &TrashedBookmark) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| SqliteStorage::upsert_trashed(connection, trashed))
+  }
+
+  fn delete_trashed(&self, uuid: &str) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| {
+      connection.execute("DELETE FROM trash WHERE uuid = ?1", params![uuid])?;
+      Ok(())
+    })
+  }
+
+  fn save_settings(&self, settings: &Settings) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| SqliteStorage::set_meta(connection, "settings", &serde_json::to_string(settings).unwrap()))
+  }
+
+  fn save_saved_searches(&self, saved_searches: &[SavedSearch]) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| SqliteStorage::set_meta(connection, "saved_searches", &serde_json::to_string(saved_searches).unwrap()))
+  }
+
+  //every part of the filter is a comparison or a range on an indexed column. prefixes are ranges instead of LIKE,
+  //which can't use an index (it ignores case). a / sorts right before 0, so tag/ up to tag0 is everything under tag
+  fn query(&self, filter: &BookmarkFilter) -> Result<Vec<Bookmark>, StorageError> {
+    let mut conditions: Vec<&str> = vec!["1"];
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(link) = &filter.link {
+      conditions.push("link = ?");
+      values.push(Box::new(link.clone()));
+    }
+    if let Some(title) = &filter.title {
+      conditions.push("title >= ? AND title < ?");
+      values.push(Box::new(title.clone()));
+      values.push(Box::new(format!("{}{}", title, char::MAX)));
+    }
+    if let Some(tag) = &filter.tag {
+      conditions.push("uuid IN (SELECT uuid FROM tags WHERE tag = ? OR (tag >= ? AND tag < ?))");
+      values.push(Box::new(tag.clone()));
+      values.push(Box::new(format!("{}/", tag)));
+      values.push(Box::new(format!("{}0", tag)));
+    }
+    if let Some(after) = filter.after {
+      conditions.push("timestamp >= ?");
+      values.push(Box::new(after as i64));
+    }
+    if let Some(before) = filter.before {
+      conditions.push("timestamp < ?");
+      values.push(Box::new(before as i64));
+    }
+    let values: Vec<&dyn ToSql> = values.iter().map(|value| value.as_ref()).collect();
+    self.with_connection(false, |connection| Ok(SqliteStorage::read_bookmarks(connection, &conditions.join(" AND "), &values)?.into_values().collect()))
+  }
+
+  fn replace(&self, stored: &Stored) -> Result<(), StorageError> {
+    self.with_connection(true, |connection| {
+      connection.execute_batch("DELETE FROM tags; DELETE FROM bookmarks; DELETE FROM trash;")?;
+      for bookmark in stored.bookmarks.values() {
+        SqliteStorage::upsert(connection, bookmark)?;
+      }
+      for trashed in stored.trash.values() {
+        SqliteStorage::upsert_trashed(connection, trashed)?;
+      }
+      SqliteStorage::set_meta(connection, "settings", &serde_json::to_string(&stored.settings).unwrap())?;
+      SqliteStorage::set_meta(connection, "saved_searches", &serde_json::to_string(&stored.saved_searches).unwrap())?;
+      SqliteStorage::set_meta(connection, "schema_version", &stored.schema_version.to_string())
+    })
+  }
+
+  fn commit(&self) -> Result<(), StorageError> {
+    self.with_connection(false, |connection| {
+      if !connection.is_autocommit() {
+        connection.execute_batch("COMMIT")?;
+      }
+      Ok(())
+    })
+  }
+
+  //hourly json snapshot, so the backups page works the same as with the json file. rows are written in place,
+  //so there is nothing to compact
+  fn maintain(&self) -> Result<(), StorageError> {
+    let backup_due: bool = self.with_connection(false, |connection| {
+      let last_backup: u64 = SqliteStorage::get_meta(connection, "last_backup")?.and_then(|timestamp| timestamp.parse().ok()).unwrap_or(0);
+      Ok(get_timestamp().saturating_sub(last_backup) >= BACKUP_INTERVAL)
+    })?;
+    if backup_due {
+      let stored: Stored = self.with_connection(false, SqliteStorage::read_all)?;
+      if let Err(error) = JsonStorage::write_backup(&stored, "backup").and_then(|_| Storage::prune_backups()) {
+        println!("{:?}", error);
+      }
+      self.with_connection(false, |connection| SqliteStorage::set_meta(connection, "last_backup", &get_timestamp().to_string()))?;
+    }
+    Ok(())
+  }
+
+  //stored_corrupt_<timestamp>.sqlite, along with its journal files
+  fn move_aside(&self) -> Result<(), StorageError> {
+    //closed first, and kept locked so nothing opens it again halfway through
+    let mut connection = self.connection.lock().unwrap();
+    *connection = None;
+    let timestamp: u64 = get_timestamp();
+    let stem: String = self.path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let file_name: String = self.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+      let path: PathBuf = self.path.with_file_name(format!("{}{}", file_name, suffix));
+      if path.is_file() {
+        rename(&path, path.with_file_name(format!("{}_corrupt_{}.sqlite{}", stem, timestamp, suffix))).map_err(|_| StorageError::WriteError)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn describe(&self) -> String {
+    format!("SQLite database at {}", self.path.display())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::remove_dir_all;
+
+  use super::*;
+
+  fn bookmark(uuid: &str, tags: &[&str], timestamp: u64) -> Bookmark {
+    Bookmark {
+      title: format!("Bookmark {}", uuid),
+      link: format!("https://example.com/{}", uuid),
+      note: None,
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      uuid: uuid.to_string(),
+      timestamp,
+    }
+  }
+
+  //a database of its own in the temp dir, removed again when the test is done
+  struct TestDatabase {
+    dir: PathBuf,
+  }
+
+  impl TestDatabase {
+    fn new(name: &str) -> TestDatabase {
+      let dir: PathBuf = std::env::temp_dir().join(format!("reservoir_test_{}_{}", name, std::process::id()));
+      let _ = remove_dir_all(&dir);
+      TestDatabase { dir }
+    }
+
+    //a fresh connection each time, so reading back goes through the file
+    fn open(&self) -> SqliteStorage {
+      SqliteStorage::at(self.dir.join("stored.sqlite"))
+    }
+  }
+
+  impl Drop for TestDatabase {
+    fn drop(&mut self) {
+      let _ = remove_dir_all(&self.dir);
+    }
+  }
+
+  fn sorted_uuids(bookmarks: &[Bookmark]) -> Vec<String> {
+    let mut uuids: Vec<String> = bookmarks.iter().map(|bookmark| bookmark.uuid.clone()).collect();
+    uuids.sort();
+    uuids
+  }
+
+  #[test]
+  fn upserts_and_deletes_bookmarks() {
+    let database: TestDatabase = TestDatabase::new("upsert");
+    let storage: SqliteStorage = database.open();
+    storage.upsert_bookmark(&bookmark("a", &["one", "two"], 1)).unwrap();
+    storage.upsert_bookmark(&bookmark("b", &[], 2)).unwrap();
+    storage.commit().unwrap();
+    storage.upsert_bookmark(&bookmark("a", &["three", "one"], 3)).unwrap();
+    storage.delete_bookmark("b").unwrap();
+    storage.commit().unwrap();
+    let stored: Stored = database.open().load().unwrap();
+    assert_eq!(stored.bookmarks.len(), 1);
+    assert_eq!(stored.bookmarks["a"], bookmark("a", &["three", "one"], 3));
+  }
+
+  #[test]
+  fn keeps_the_trash() {
+    let database: TestDatabase = TestDatabase::new("trash");
+    let storage: SqliteStorage = database.open();
+    let trashed = |uuid: &str| TrashedBookmark { bookmark: bookmark(uuid, &["old"], 1), deleted: 5 };
+    storage.upsert_trashed(&trashed("a")).unwrap();
+    storage.upsert_trashed(&trashed("b")).unwrap();
+    storage.delete_trashed("b").unwrap();
+    storage.commit().unwrap();
+    let stored: Stored = database.open().load().unwrap();
+    assert!(stored.bookmarks.is_empty());
+    assert_eq!(stored.trash.len(), 1);
+    assert_eq!(stored.trash["a"], trashed("a"));
+  }
+
+  #[test]
+  fn loads_what_was_replaced() {
+    let database: TestDatabase = TestDatabase::new("replace");
+    let storage: SqliteStorage = database.open();
+    storage.upsert_bookmark(&bookmark("gone", &[], 1)).unwrap();
+    storage.commit().unwrap();
+    let settings: Settings = Settings {
+      trash_days: 3,
+      lowercase_tags: true,
+      ..Settings::default()
+    };
+    let mut stored: Stored = Stored {
+      settings: settings.clone(),
+      ..Stored::empty()
+    };
+    for uuid in ["a", "b"] {
+      stored.bookmarks.insert(uuid.to_string(), bookmark(uuid, &["x/y", "z"], 2));
+    }
+    storage.replace(&stored).unwrap();
+    storage.commit().unwrap();
+    let loaded: Stored = database.open().load().unwrap();
+    assert_eq!(loaded.schema_version, SCHEMA_VERSION);
+    assert_eq!(loaded.bookmarks, stored.bookmarks);
+    assert!(loaded.trash.is_empty());
+    assert_eq!(loaded.settings, settings);
+    assert!(loaded.saved_searches.is_empty());
+  }
+
+  #[test]
+  fn writes_that_are_not_committed_are_lost() {
+    let database: TestDatabase = TestDatabase::new("uncommitted");
+    let storage: SqliteStorage = database.open();
+    storage.upsert_bookmark(&bookmark("a", &[], 1)).unwrap();
+    storage.commit().unwrap();
+    storage.upsert_bookmark(&bookmark("b", &[], 1)).unwrap();
+    drop(storage);
+    let stored: Stored = database.open().load().unwrap();
+    assert_eq!(stored.bookmarks.keys().collect::<Vec<&String>>(), vec!["a"]);
+  }
+
+  #[test]
+  fn a_failed_write_rolls_back_the_batch() {
+    let database: TestDatabase = TestDatabase::new("rollback");
+    let storage: SqliteStorage = database.open();
+    storage.upsert_bookmark(&bookmark("a", &[], 1)).unwrap();
+    let failed: Result<(), StorageError> = storage.with_connection(true, |connection| {
+      connection.execute("INSERT INTO missing (uuid) VALUES ('b')", [])?;
+      Ok(())
+    });
+    assert!(matches!(failed, Err(StorageError::DatabaseError(_))));
+    storage.commit().unwrap();
+    assert!(database.open().load().unwrap().bookmarks.is_empty());
+    //and the next batch starts clean
+    storage.upsert_bookmark(&bookmark("c", &[], 1)).unwrap();
+    storage.commit().unwrap();
+    assert_eq!(database.open().load().unwrap().bookmarks.keys().collect::<Vec<&String>>(), vec!["c"]);
+  }
+
+  #[test]
+  fn moving_aside_starts_a_new_library() {
+    let database: TestDatabase = TestDatabase::new("move_aside");
+    let storage: SqliteStorage = database.open();
+    storage.upsert_bookmark(&bookmark("a", &[], 1)).unwrap();
+    storage.commit().unwrap();
+    storage.move_aside().unwrap();
+    assert!(!database.dir.join("stored.sqlite").exists());
+    let moved: Vec<String> = std::fs::read_dir(&database.dir).unwrap().flatten().map(|entry| entry.file_name().to_string_lossy().to_string()).collect();
+    assert!(moved.iter().any(|name| name.starts_with("stored_corrupt_") && name.ends_with(".sqlite")), "{:?}", moved);
+    //the moved file still has the bookmark
+    let moved_path: PathBuf = database.dir.join(moved.iter().find(|name| name.ends_with(".sqlite")).unwrap());
+    assert_eq!(SqliteStorage::at(moved_path).load().unwrap().bookmarks.len(), 1);
+    storage.replace(&Stored::empty()).unwrap();
+    storage.commit().unwrap();
+    assert!(database.open().load().unwrap().bookmarks.is_empty());
+  }
+
+  #[test]
+  fn takes_over_a_json_library() {
+    let database: TestDatabase = TestDatabase::new("migration");
+    //a stored.json from before schema versions
+    let (stored, migrated_from) = JsonStorage::parse(r#"{
+      "bookmarks": {
+        "a": { "title": "Rust", "link": "www.rust-lang.org", "tags": [" Lang / Rust/", "rust"], "uuid": "a", "timestamp": 1 },
+        "b": { "title": "Mail", "link": "mailto:me@example.com", "tags": [], "uuid": "b", "timestamp": 2 }
+      }
+    }"#).unwrap();
+    assert_eq!(migrated_from, Some(0));
+    let storage: SqliteStorage = database.open();
+    storage.replace(&stored).unwrap();
+    storage.commit().unwrap();
+    let loaded: Stored = database.open().load().unwrap();
+    assert_eq!(loaded.schema_version, SCHEMA_VERSION);
+    assert_eq!(loaded.bookmarks, stored.bookmarks);
+    assert_eq!(loaded.bookmarks["a"].link, "https://www.rust-lang.org");
+    assert_eq!(loaded.bookmarks["a"].tags, vec!["Lang/Rust", "rust"]);
+    assert_eq!(loaded.settings, Settings::default());
+  }
+
+  #[test]
+  fn query_matches_the_filter() {
+    let database: TestDatabase = TestDatabase::new("query");
+    let storage: SqliteStorage = database.open();
+    let bookmarks: Vec<Bookmark> = vec![
+      bookmark("a", &["lang"], 10),
+      bookmark("b", &["lang/rust"], 20),
+      bookmark("c", &["language"], 30),
+      bookmark("d", &["Lang"], 40),
+    ];
+    for bookmark in &bookmarks {
+      storage.upsert_bookmark(bookmark).unwrap();
+    }
+    storage.commit().unwrap();
+    let filters: Vec<BookmarkFilter> = vec![
+      BookmarkFilter::default(),
+      BookmarkFilter { tag: Some("lang".to_string()), ..BookmarkFilter::default() },
+      BookmarkFilter { tag: Some("lang/rust".to_string()), ..BookmarkFilter::default() },
+      BookmarkFilter { link: Some("https://example.com/c".to_string()), ..BookmarkFilter::default() },
+      BookmarkFilter { title: Some("Bookmark".to_string()), after: Some(20), before: Some(40), ..BookmarkFilter::default() },
+      BookmarkFilter { title: Some("bookmark".to_string()), ..BookmarkFilter::default() },
+    ];
+    let expected: Vec<Vec<&str>> = vec![vec!["a", "b", "c", "d"], vec!["a", "b"], vec!["b"], vec!["c"], vec!["b", "c"], vec![]];
+    for (filter, expected) in filters.iter().zip(expected) {
+      let found: Vec<String> = sorted_uuids(&storage.query(filter).unwrap());
+      assert_eq!(found, expected, "{:?}", filter);
+      //same answer as the json backend
+      let matching: Vec<Bookmark> = bookmarks.iter().filter(|bookmark| filter.matches(bookmark)).cloned().collect();
+      assert_eq!(found, sorted_uuids(&matching), "{:?}", filter);
+    }
+  }
+}
@@ -1,13 +1,25 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{ HashMap, HashSet };
+use std::fs::{ create_dir_all, read_dir, read_to_string, remove_file };
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use directories_next;
 use serde_json;
+use serde_json::{ json, Value };
 use serde::{ Serialize, Deserialize };
 use async_std::prelude::*;
-use async_std::fs::{ create_dir_all, File };
+use async_std::fs::File;
+use async_std::task;
 
-use crate::utils::{ gen_uuid, get_timestamp };
+use crate::utils::{ canonical_link, full_link, gen_uuid, get_timestamp, link_scheme };
+use crate::json_storage::JsonStorage;
+use crate::sqlite_storage::SqliteStorage;
+use crate::bookmark_bar::{ ExportFormat, SearchOptions, SortOptions };
+use crate::export;
+use crate::search_index::SearchIndex;
+use crate::tags::is_under;
 
 #[derive(Clone, Debug)]
 pub enum StorageError {
@@ -15,9 +27,52 @@ pub enum StorageError {
   ReadError,
   OpenError,
   WriteError,
+  //stored.json was written by a newer version of reservoir
+  VersionError,
+  //stored.json isn't valid json, or doesn't look like a library. line and column are 1 based, 0 if unknown
+  ParseError { line: usize, column: usize, message: String },
+  NoBackupError,
+  //something went wrong inside the sqlite database
+  DatabaseError(String),
+  //a file being imported couldn't be read
+  ImportError(String),
+  //the export file couldn't be written
+  ExportError(String),
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+impl StorageError {
+  pub fn from_json(error: serde_json::Error) -> StorageError {
+    //line() is 0 for errors that don't come from reading text (eg from_value)
+    let message: String = error.to_string();
+    StorageError::ParseError {
+      line: error.line(),
+      column: error.column(),
+      //the position is shown separately
+      message: message.rsplit_once(" at line ").map(|(message, _)| message.to_string()).unwrap_or(message),
+    }
+  }
+}
+
+impl std::fmt::Display for StorageError {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let content: String = match self {
+      StorageError::CreateError => "Could not create the library file".to_string(),
+      StorageError::ReadError => "Could not read the library file".to_string(),
+      StorageError::OpenError => "Could not open the library file".to_string(),
+      StorageError::WriteError => "Could not write the library file".to_string(),
+      StorageError::VersionError => "The library was saved by a newer version of reservoir".to_string(),
+      StorageError::ParseError { line: 0, message, .. } => format!("The library file is corrupt: {}", message),
+      StorageError::ParseError { line, column, message } => format!("The library file is corrupt at line {}, column {}: {}", line, column, message),
+      StorageError::NoBackupError => "No usable backup was found".to_string(),
+      StorageError::DatabaseError(message) => format!("The library database had an error: {}", message),
+      StorageError::ImportError(message) => format!("Could not import: {}", message),
+      StorageError::ExportError(message) => format!("Could not export: {}", message),
+    };
+    write!(formatter, "{}", content)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Bookmark {
   pub title: String,
   pub link: String,
@@ -40,62 +95,432 @@ impl Bookmark {
   }
 }
 
+//a deleted bookmark, kept until it is restored or purged
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct TrashedBookmark {
+  #[serde(flatten)]
+  pub bookmark: Bookmark,
+  pub deleted: u64,
+}
+
+//new settings only need a default here, not a migration
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+  //trashed bookmarks are purged after this many days
+  pub trash_days: u64,
+  //where the last export was written, the export chooser starts there
+  pub export_dir: Option<PathBuf>,
+  //query parameters that don't change what a link points to, ignored when looking for duplicates
+  pub tracking_parameters: Vec<String>,
+  //links are only opened if they start with one of these, so a bookmark can't launch any program that registered a scheme
+  pub allowed_schemes: Vec<String>,
+  //how close (in percent) a word has to be to count as a match in fuzzy search
+  pub fuzzy_similarity: u64,
+  //tags are lowercased when added or edited
+  pub lowercase_tags: bool,
+  //what tags may have in them besides letters, numbers and spaces
+  pub tag_characters: String,
+}
+
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      trash_days: 30,
+      export_dir: None,
+      tracking_parameters: ["utm_*", "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid", "_hsenc", "_hsmi", "ref_src"].iter().map(|parameter| parameter.to_string()).collect(),
+      allowed_schemes: ["https", "http", "file", "mailto", "ftp"].iter().map(|scheme| scheme.to_string()).collect(),
+      fuzzy_similarity: 70,
+      lowercase_tags: false,
+      tag_characters: "-_./+#&".to_string(),
+    }
+  }
+}
+
+//a search kept under a name, shown as a chip under the bar
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SavedSearch {
+  pub name: String,
+  pub search_option: SearchOptions,
+  pub sort_option: SortOptions,
+  //as typed in the search box
+  pub query: String,
+}
+
+//bump this and add a step to MIGRATIONS whenever the stored.json format changes
+pub const SCHEMA_VERSION: u32 = 6;
+
+//MIGRATIONS[n] takes a library from schema version n to n+1, schema_version itself is set by migrate.
+//backends that aren't json convert to a Value and back to run these
+const MIGRATIONS: [fn(&mut Value); 6] = [
+  //v0 is the original format, which is the same as v1 minus the schema_version field
+  |_value| {},
+  //v2 adds the trash and settings
+  |value| {
+    value["trash"] = json!({});
+    value["settings"] = serde_json::to_value(Settings::default()).unwrap();
+  },
+  //v3 stores links with their scheme, before this https:// was cut off when saving
+  |value| {
+    for map_key in ["bookmarks", "trash"] {
+      if let Value::Object(map) = &mut value[map_key] {
+        for bookmark in map.values_mut() {
+          if let Some(link) = bookmark["link"].as_str() {
+            bookmark["link"] = Value::from(full_link(link));
+          }
+        }
+      }
+    }
+  },
+  //v4 adds saved searches
+  |value| {
+    value["saved_searches"] = json!([]);
+  },
+  //v5 cleans up tags, before this they were stored as typed between the commas (" async", "", "Rust" next to "rust")
+  |value| {
+    migrate_tags(value, |tag| tag.trim().to_string());
+  },
+  //v6 makes tags with a / a hierarchy, "lang / rust/" is lang/rust
+  |value| {
+    migrate_tags(value, |tag| tag.split('/').map(str::trim).filter(|part| !part.is_empty()).collect::<Vec<&str>>().join("/"));
+  },
+];
+
+//rewrite every tag of bookmarks and trashed bookmarks, dropping the empty ones and repeats (ignoring case, the first is kept).
+//the steps don't share the tags module, so changing how tags are normalized later doesn't change what old steps did
+fn migrate_tags(value: &mut Value, rewrite: fn(&str) -> String) {
+  for map_key in ["bookmarks", "trash"] {
+    if let Value::Object(map) = &mut value[map_key] {
+      for bookmark in map.values_mut() {
+        if let Value::Array(tags) = &bookmark["tags"] {
+          let mut seen: HashSet<String> = HashSet::new();
+          let tags: Vec<String> = tags.iter().filter_map(|tag| tag.as_str()).map(rewrite).filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase())).collect();
+          bookmark["tags"] = json!(tags);
+        }
+      }
+    }
+  }
+}
+
+//run every migration step between the library's version and the current one, in order
+pub fn migrate(value: &mut Value, from_version: u32) {
+  for version in from_version..SCHEMA_VERSION {
+    MIGRATIONS[version as usize](value);
+    value["schema_version"] = Value::from(version + 1);
+  }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Stored {
+  pub schema_version: u32,
   //key is link?
   pub bookmarks: HashMap<String, Bookmark>,
+  pub trash: HashMap<String, TrashedBookmark>,
+  pub settings: Settings,
+  pub saved_searches: Vec<SavedSearch>,
+}
+
+impl Stored {
+  pub fn empty() -> Stored {
+    Stored {
+      schema_version: SCHEMA_VERSION,
+      bookmarks: HashMap::new(),
+      trash: HashMap::new(),
+      settings: Settings::default(),
+      saved_searches: Vec::new(),
+    }
+  }
+}
+
+//a change to the library that still needs to be written by the backend
+#[derive(Clone, Debug)]
+pub enum Operation {
+  Upsert(Bookmark),
+  Delete(String),
+  UpsertTrashed(TrashedBookmark),
+  DeleteTrashed(String),
+  Settings(Settings),
+  //the whole list, it is short
+  SavedSearches(Vec<SavedSearch>),
+  //throw everything away and write this instead (restoring a backup, or after a failed save)
+  Replace(Stored),
+}
+
+//what StorageBackend::query looks for, every part that is set has to match. compared as stored (case matters),
+//so the sqlite backend can answer it from its indexes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookmarkFilter {
+  pub link: Option<String>,
+  //titles starting with this
+  pub title: Option<String>,
+  //this tag or one under it
+  pub tag: Option<String>,
+  //added at or after this
+  pub after: Option<u64>,
+  //added before this
+  pub before: Option<u64>,
+}
+
+impl BookmarkFilter {
+  pub fn matches(&self, bookmark: &Bookmark) -> bool {
+    self.link.as_ref().map(|link| bookmark.link == *link).unwrap_or(true)
+      && self.title.as_ref().map(|title| bookmark.title.starts_with(title.as_str())).unwrap_or(true)
+      && self.tag.as_ref().map(|tag| bookmark.tags.iter().any(|bookmark_tag| is_under(bookmark_tag, tag))).unwrap_or(true)
+      && self.after.map(|after| bookmark.timestamp >= after).unwrap_or(true)
+      && self.before.map(|before| bookmark.timestamp < before).unwrap_or(true)
+  }
+}
+
+//where the library actually lives. methods block, so Storage only calls them from task::spawn_blocking
+pub trait StorageBackend: Send + Sync {
+  fn load(&self) -> Result<Stored, StorageError>;
+  fn upsert_bookmark(&self, bookmark: &Bookmark) -> Result<(), StorageError>;
+  fn delete_bookmark(&self, uuid: &str) -> Result<(), StorageError>;
+  fn upsert_trashed(&self, trashed: &TrashedBookmark) -> Result<(), StorageError>;
+  fn delete_trashed(&self, uuid: &str) -> Result<(), StorageError>;
+  fn save_settings(&self, settings: &Settings) -> Result<(), StorageError>;
+  fn save_saved_searches(&self, saved_searches: &[SavedSearch]) -> Result<(), StorageError>;
+  //the bookmarks matching the filter, straight from the backend. the ui searches Storage's SearchIndex instead,
+  //this is for looking things up without going through the loaded library
+  #[allow(dead_code)]
+  fn query(&self, filter: &BookmarkFilter) -> Result<Vec<Bookmark>, StorageError>;
+  fn replace(&self, stored: &Stored) -> Result<(), StorageError>;
+  //make everything since the last commit durable
+  fn commit(&self) -> Result<(), StorageError>;
+  //housekeeping that doesn't have to happen during a save (compacting, backups). called every so often while the app is open
+  fn maintain(&self) -> Result<(), StorageError>;
+  //rename the library's files out of the way when they can't be loaded (instead of deleting them, there might be
+  //something worth saving in there). the next write starts a new library
+  fn move_aside(&self) -> Result<(), StorageError>;
+  fn describe(&self) -> String;
+}
+
+fn apply_operations(backend: &dyn StorageBackend, operations: Vec<Operation>) -> Result<(), StorageError> {
+  for operation in operations {
+    match operation {
+      Operation::Upsert(bookmark) => backend.upsert_bookmark(&bookmark)?,
+      Operation::Delete(uuid) => backend.delete_bookmark(&uuid)?,
+      Operation::UpsertTrashed(trashed) => backend.upsert_trashed(&trashed)?,
+      Operation::DeleteTrashed(uuid) => backend.delete_trashed(&uuid)?,
+      Operation::Settings(settings) => backend.save_settings(&settings)?,
+      Operation::SavedSearches(saved_searches) => backend.save_saved_searches(&saved_searches)?,
+      Operation::Replace(stored) => backend.replace(&stored)?,
+    }
+  }
+  backend.commit()
+}
+
+//a backup of the library is made when it is opened and then at most every BACKUP_INTERVAL while it changes.
+//the newest BACKUP_KEEP_RECENT are always kept, older ones only if they are the newest backup of their day,
+//and only for BACKUP_KEEP_DAYS days
+pub const BACKUP_INTERVAL: u64 = 60 * 60;
+const BACKUP_KEEP_RECENT: usize = 20;
+const BACKUP_KEEP_DAYS: u64 = 30;
+pub const SECONDS_IN_DAY: u64 = 60 * 60 * 24;
+
+#[derive(Clone, Debug)]
+pub struct Backup {
+  pub path: PathBuf,
+  pub timestamp: u64,
+  pub bookmark_count: usize,
 }
 
 pub struct Storage {
   pub stored: Option<Stored>,
+  backend: Arc<dyn StorageBackend>,
+  //changes made since the last save was started
+  pending: Vec<Operation>,
+  //only one save runs at a time, so saves can never land out of order. changes made meanwhile wait in pending
+  saving: bool,
+  //operations that undo the changes made since take_changes was last called, in the order they were made
+  changes: Vec<Operation>,
+  //the bookmarks, indexed for searching. follows every change made through apply
+  pub index: SearchIndex,
 }
 
 impl Storage {
   pub fn new() -> Storage {
     Storage {
       stored: None,
+      backend: Storage::open_backend(),
+      pending: Vec::new(),
+      saving: false,
+      changes: Vec::new(),
+      index: SearchIndex::new(),
+    }
+  }
+
+  //the library finished loading
+  pub fn loaded(&mut self, stored: Stored) {
+    self.index = SearchIndex::build(stored.bookmarks.values());
+    self.stored = Some(stored);
+  }
+
+  //sqlite once the library has been moved there, the json file otherwise
+  fn open_backend() -> Arc<dyn StorageBackend> {
+    if SqliteStorage::path().is_file() {
+      Arc::new(SqliteStorage::new())
+    } else {
+      Arc::new(JsonStorage::new())
     }
   }
 
-  fn path() -> PathBuf {
-    let mut save_path: PathBuf;
+  pub fn data_dir() -> PathBuf {
     let project_dir = directories_next::ProjectDirs::from("rs", "prussiacorp", "reservoir");
-    if project_dir.is_some() {
-      save_path = project_dir.unwrap().data_dir().into();
+    if let Some(project_dir) = project_dir {
+      project_dir.data_dir().into()
     } else {
-      save_path = std::env::current_dir().unwrap_or_default();
+      std::env::current_dir().unwrap_or_default()
+    }
+  }
+
+  pub fn create_data_dir() -> Result<(), StorageError> {
+    let data_dir: PathBuf = Storage::data_dir();
+    if !data_dir.is_dir() {
+      create_dir_all(data_dir).map_err(|_| StorageError::CreateError)?;
     }
-    save_path.push("stored.json");
-    save_path
+    Ok(())
+  }
+
+  pub fn describe_backend(&self) -> String {
+    self.backend.describe()
+  }
+
+  pub fn is_sqlite(&self) -> bool {
+    SqliteStorage::path().is_file()
   }
 
-  fn empty_json() -> String {
-    "{\n  \"bookmarks\": {}\n}".to_string()
+  fn backup_dir() -> PathBuf {
+    Storage::data_dir().join("backups")
   }
 
-  pub async fn load() -> Result<Stored, StorageError> {
-    let save_path = Storage::path();
-    //todo: find some way to make this not mutable idk
-    let mut save_file: File;
-    let save_path_parent = save_path.parent().unwrap();
-    if !save_path_parent.is_dir() {
-      create_dir_all(save_path_parent).await.map_err(|_| StorageError::CreateError)?;
+  //somewhere to write a new backup to. prefix is "backup" for the rolling ones
+  pub fn new_backup_path(prefix: &str) -> Result<PathBuf, StorageError> {
+    let backup_dir: PathBuf = Storage::backup_dir();
+    if !backup_dir.is_dir() {
+      create_dir_all(&backup_dir).map_err(|_| StorageError::CreateError)?;
     }
-    if !save_path.is_file() {
-      //create file
-      save_file = File::create(save_path.clone()).await.map_err(|_| StorageError::CreateError)?;
-      save_file.write_all(
-        Storage::empty_json().as_bytes()
-      ).await.map_err(|_| StorageError::WriteError)?;
+    Ok(backup_dir.join(format!("{}_{}.json", prefix, get_timestamp())))
+  }
+
+  //every json file in the backups folder, along with when it was made. newest first
+  pub fn backup_files() -> Vec<(u64, PathBuf)> {
+    let mut backups: Vec<(u64, PathBuf)> = Vec::new();
+    if let Ok(entries) = read_dir(Storage::backup_dir()) {
+      for entry in entries.flatten() {
+        let path: PathBuf = entry.path();
+        if path.extension().unwrap_or_default() != "json" {
+          continue;
+        }
+        //file names end in _<timestamp>.json, fall back to modified time if someone renamed them
+        let name_timestamp: Option<u64> = path.file_stem().unwrap_or_default().to_string_lossy().rsplit('_').next().and_then(|timestamp| timestamp.parse().ok());
+        let timestamp: u64 = match name_timestamp {
+          Some(timestamp) => timestamp,
+          None => match entry.metadata().map(|metadata| metadata.modified()) {
+            Ok(Ok(modified)) => modified.duration_since(SystemTime::UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0),
+            _ => 0,
+          },
+        };
+        backups.push((timestamp, path));
+      }
     }
-    //we must open the file even if we just created it because of permission
-    save_file = File::open(save_path).await.map_err(|_| StorageError::OpenError)?;
-    let mut contents: String = String::new();
-    if let Err(error) = save_file.read_to_string(&mut contents).await {
-      println!("{:?}", error);
+    backups.sort_unstable_by_key(|backup| Reverse(backup.0));
+    backups
+  }
+
+  //when the newest rolling backup was made, 0 if there is none
+  pub fn last_backup_time() -> u64 {
+    Storage::backup_files().into_iter().find(|(_, path)| path.file_name().unwrap_or_default().to_string_lossy().starts_with("backup_")).map(|(timestamp, _)| timestamp).unwrap_or(0)
+  }
+
+  //delete whatever rolling backups the retention policy doesn't keep
+  pub fn prune_backups() -> Result<(), StorageError> {
+    let now: u64 = get_timestamp();
+    let mut kept_days: Vec<u64> = Vec::new();
+    //pre migration backups are never deleted
+    let rotated = Storage::backup_files().into_iter().filter(|(_, path)| path.file_name().unwrap_or_default().to_string_lossy().starts_with("backup_"));
+    for (index, (timestamp, path)) in rotated.enumerate() {
+      let day: u64 = timestamp / SECONDS_IN_DAY;
+      let keep_daily: bool = now.saturating_sub(timestamp) < BACKUP_KEEP_DAYS * SECONDS_IN_DAY && !kept_days.contains(&day);
+      if keep_daily {
+        kept_days.push(day);
+      }
+      if index >= BACKUP_KEEP_RECENT && !keep_daily {
+        remove_file(path).map_err(|_| StorageError::WriteError)?;
+      }
     }
-    save_file.read_to_string(&mut contents).await.map_err(|_| StorageError::ReadError)?;
-    Ok(serde_json::from_str(&contents).unwrap())
+    Ok(())
+  }
+
+  fn read_backup_sync(path: PathBuf) -> Result<Stored, StorageError> {
+    let contents: String = read_to_string(path).map_err(|_| StorageError::ReadError)?;
+    Ok(JsonStorage::parse(&contents)?.0)
+  }
+
+  //for the restore view. only counts the bookmarks, so old backups don't need migrating just to be listed
+  pub async fn list_backups() -> Result<Vec<Backup>, StorageError> {
+    task::spawn_blocking(|| {
+      let mut backups: Vec<Backup> = Vec::new();
+      for (timestamp, path) in Storage::backup_files() {
+        let contents: String = match read_to_string(&path) {
+          Ok(contents) => contents,
+          Err(_) => continue,
+        };
+        if let Ok(value) = serde_json::from_str::<Value>(&contents) {
+          backups.push(Backup {
+            path,
+            timestamp,
+            bookmark_count: value.get("bookmarks").and_then(Value::as_object).map(|bookmarks| bookmarks.len()).unwrap_or(0),
+          });
+        }
+      }
+      Ok(backups)
+    }).await
+  }
+
+  pub async fn read_backup(path: PathBuf) -> Result<Stored, StorageError> {
+    task::spawn_blocking(move || Storage::read_backup_sync(path)).await
+  }
+
+  pub fn load(&self) -> impl Future<Output = Result<Stored, StorageError>> {
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    task::spawn_blocking(move || backend.load())
+  }
+
+  //recovery option: newest backup that can actually be loaded
+  pub fn restore_latest_backup(&self) -> impl Future<Output = Result<Stored, StorageError>> {
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    task::spawn_blocking(move || {
+      for (_, backup_path) in Storage::backup_files() {
+        if let Ok(stored) = Storage::read_backup_sync(backup_path) {
+          backend.move_aside()?;
+          apply_operations(backend.as_ref(), vec![Operation::Replace(stored.clone())])?;
+          return Ok(stored);
+        }
+      }
+      Err(StorageError::NoBackupError)
+    })
+  }
+
+  //recovery option: empty library, keeping the broken file around
+  pub fn start_fresh(&self) -> impl Future<Output = Result<Stored, StorageError>> {
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    task::spawn_blocking(move || {
+      backend.move_aside()?;
+      apply_operations(backend.as_ref(), vec![Operation::Replace(Stored::empty())])?;
+      Ok(Stored::empty())
+    })
+  }
+
+  //recovery option: pull every bookmark that is still intact out of a broken stored.json
+  pub fn salvage(&self) -> impl Future<Output = Result<Stored, StorageError>> {
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    task::spawn_blocking(move || {
+      let stored: Stored = JsonStorage::salvage()?;
+      backend.move_aside()?;
+      apply_operations(backend.as_ref(), vec![Operation::Replace(stored.clone())])?;
+      Ok(stored)
+    })
   }
 
   /*
@@ -110,36 +535,262 @@ impl Storage {
   }
   */
 
-  //would be nice to change to &Stored or some kind of pointer
-  pub async fn save_async_separate(stored: Stored) -> Result<(), StorageError> {
-    let mut save_file: File = File::create(Storage::path()).await.map_err(|_| StorageError::OpenError)?;
-    save_file.write_all(serde_json::to_string_pretty(&stored).unwrap().as_bytes()).await.map_err(|_| StorageError::WriteError)?;
-    Ok(())
+  //hand the pending changes to the backend. none if there is nothing to save, or a save is already running
+  //(call save_done when it finishes, which starts the next one if needed)
+  pub fn save(&mut self) -> Option<impl Future<Output = Result<(), StorageError>>> {
+    if self.saving || self.pending.is_empty() {
+      return None;
+    }
+    self.saving = true;
+    let operations: Vec<Operation> = std::mem::take(&mut self.pending);
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    Some(task::spawn_blocking(move || apply_operations(backend.as_ref(), operations)))
+  }
+
+  pub fn save_done(&mut self, result: &Result<(), StorageError>) {
+    self.saving = false;
+    if result.is_err() {
+      //no idea how much of it got written, so write everything next time
+      self.pending = vec![Operation::Replace(self.stored.as_ref().unwrap().clone())];
+    }
+  }
+
+  //run the backend's housekeeping in the background. none before the library is loaded or while a save is running
+  pub fn maintain(&mut self) -> Option<impl Future<Output = Result<(), StorageError>>> {
+    if self.saving || self.stored.is_none() {
+      return None;
+    }
+    let backend: Arc<dyn StorageBackend> = self.backend.clone();
+    Some(task::spawn_blocking(move || backend.maintain()))
+  }
+
+  //one shot move of the library from stored.json into a sqlite database. none if a save is running
+  pub fn migrate_to_sqlite(&mut self) -> Option<impl Future<Output = Result<(), StorageError>>> {
+    if self.saving || self.is_sqlite() {
+      return None;
+    }
+    //stop saves to the json file while this runs, they are applied to the database afterwards
+    self.saving = true;
+    let stored: Stored = self.stored.as_ref().unwrap().clone();
+    Some(task::spawn_blocking(move || {
+      let sqlite: SqliteStorage = SqliteStorage::new();
+      if let Err(error) = apply_operations(&sqlite, vec![Operation::Replace(stored)]) {
+        //don't leave a half filled database around, it would be picked up next start
+        drop(sqlite);
+        let _ = remove_file(SqliteStorage::path());
+        return Err(error);
+      }
+      JsonStorage::retire()
+    }))
+  }
+
+  pub fn migrate_to_sqlite_done(&mut self, result: &Result<(), StorageError>) {
+    self.saving = false;
+    if result.is_ok() {
+      self.backend = Storage::open_backend();
+    }
+  }
+
+  //every change to the library goes through here: it is made in memory, queued for saving,
+  //and the operation that reverses it is remembered for undo
+  pub fn apply(&mut self, operation: Operation) {
+    let stored: &mut Stored = self.stored.as_mut().unwrap();
+    let inverse: Option<Operation> = match &operation {
+      Operation::Upsert(bookmark) => match stored.bookmarks.insert(bookmark.uuid.clone(), bookmark.clone()) {
+        Some(previous) => Some(Operation::Upsert(previous)),
+        None => Some(Operation::Delete(bookmark.uuid.clone())),
+      },
+      Operation::Delete(uuid) => stored.bookmarks.remove(uuid).map(Operation::Upsert),
+      Operation::UpsertTrashed(trashed) => match stored.trash.insert(trashed.bookmark.uuid.clone(), trashed.clone()) {
+        Some(previous) => Some(Operation::UpsertTrashed(previous)),
+        None => Some(Operation::DeleteTrashed(trashed.bookmark.uuid.clone())),
+      },
+      Operation::DeleteTrashed(uuid) => stored.trash.remove(uuid).map(Operation::UpsertTrashed),
+      Operation::Settings(settings) => Some(Operation::Settings(std::mem::replace(&mut stored.settings, settings.clone()))),
+      Operation::SavedSearches(saved_searches) => Some(Operation::SavedSearches(std::mem::replace(&mut stored.saved_searches, saved_searches.clone()))),
+      Operation::Replace(new_stored) => Some(Operation::Replace(std::mem::replace(stored, new_stored.clone()))),
+    };
+    if let Some(inverse) = inverse {
+      self.changes.push(inverse);
+    }
+    match &operation {
+      Operation::Upsert(bookmark) => self.index.insert(bookmark),
+      Operation::Delete(uuid) => self.index.remove(uuid),
+      Operation::Replace(new_stored) => self.index = SearchIndex::build(new_stored.bookmarks.values()),
+      _ => {},
+    }
+    //settings and saved searches are written whole, so only the newest of a run of them needs saving (typing in a box)
+    let replaces_last: bool = matches!((self.pending.last(), &operation), (Some(Operation::Settings(_)), Operation::Settings(_)) | (Some(Operation::SavedSearches(_)), Operation::SavedSearches(_)));
+    if replaces_last {
+      self.pending.pop();
+    }
+    self.pending.push(operation);
+  }
+
+  //how to undo everything since the last call
+  pub fn take_changes(&mut self) -> Vec<Operation> {
+    std::mem::take(&mut self.changes)
+  }
+
+  pub fn replace(&mut self, stored: Stored) {
+    self.apply(Operation::Replace(stored));
   }
 
   //also do this for edit bookmark
   pub fn add_bookmark(&mut self, bookmark: Bookmark) {
-    self.stored.as_mut().unwrap().bookmarks.insert((&bookmark.uuid).to_string(), bookmark);
+    self.apply(Operation::Upsert(bookmark));
+  }
+
+  pub fn canonical_link(&self, link: &str) -> String {
+    canonical_link(link, &self.stored.as_ref().unwrap().settings.tracking_parameters)
   }
 
+  //whether the link's scheme is allowed in the settings
+  pub fn can_open(&self, link: &str) -> bool {
+    let allowed_schemes: &Vec<String> = &self.stored.as_ref().unwrap().settings.allowed_schemes;
+    link_scheme(link).map(|scheme| allowed_schemes.iter().any(|allowed| allowed.eq_ignore_ascii_case(&scheme))).unwrap_or(false)
+  }
+
+  //links are unique, adding or importing a link that is already there is skipped
+  pub fn find_duplicate(&self, link: &str) -> Option<&Bookmark> {
+    let canonical: String = self.canonical_link(link);
+    self.stored.as_ref().unwrap().bookmarks.values().find(|bookmark| self.canonical_link(&bookmark.link) == canonical)
+  }
+
+  //for checking lots of links at once
+  pub fn canonical_links(&self) -> HashSet<String> {
+    self.stored.as_ref().unwrap().bookmarks.values().map(|bookmark| self.canonical_link(&bookmark.link)).collect()
+  }
+
+  //deleting only moves the bookmark to the trash
   pub fn remove_bookmark(&mut self, uuid: String) {
-    self.stored.as_mut().unwrap().bookmarks.remove(&uuid);
+    if let Some(bookmark) = self.stored.as_ref().unwrap().bookmarks.get(&uuid).cloned() {
+      self.apply(Operation::Delete(uuid));
+      self.apply(Operation::UpsertTrashed(TrashedBookmark {
+        bookmark,
+        deleted: get_timestamp(),
+      }));
+    }
   }
 
-  //would be nice to change to &Stored or some kind of pointer
-  pub async fn export(stored: Stored) -> Result<(), StorageError> {
-    if let Some(user_dirs) = directories_next::UserDirs::new() {
-      if let Some(download_path) = user_dirs.download_dir() {
-        let mut save_path: PathBuf = download_path.into();
-        save_path.push(format!("reservoir_info_{}.json", get_timestamp().to_string()));
-        let mut save_file: File = File::create(save_path).await.map_err(|_| StorageError::OpenError)?;
-        save_file.write_all(serde_json::to_string_pretty(&stored).unwrap().as_bytes()).await.map_err(|_| StorageError::WriteError)?;
-      } else {
-        return Err(StorageError::OpenError);
-      }
-    } else {
-      return Err(StorageError::OpenError);
+  pub fn restore_trashed(&mut self, uuid: String) {
+    if let Some(trashed) = self.stored.as_ref().unwrap().trash.get(&uuid).cloned() {
+      self.apply(Operation::DeleteTrashed(uuid));
+      self.apply(Operation::Upsert(trashed.bookmark));
     }
-    Ok(())
+  }
+
+  //gone for good (apart from undo)
+  pub fn purge_trashed(&mut self, uuid: String) {
+    self.apply(Operation::DeleteTrashed(uuid));
+  }
+
+  //permanently delete everything that has been in the trash longer than the setting allows.
+  //returns whether anything was purged
+  pub fn purge_expired(&mut self) -> bool {
+    let stored: &Stored = self.stored.as_ref().unwrap();
+    let cutoff: u64 = get_timestamp().saturating_sub(stored.settings.trash_days * SECONDS_IN_DAY);
+    let expired: Vec<String> = stored.trash.values().filter(|trashed| trashed.deleted < cutoff).map(|trashed| trashed.bookmark.uuid.clone()).collect();
+    for uuid in &expired {
+      self.purge_trashed(uuid.to_string());
+    }
+    !expired.is_empty()
+  }
+
+  pub fn set_settings(&mut self, settings: Settings) {
+    self.apply(Operation::Settings(settings));
+  }
+
+  pub fn set_saved_searches(&mut self, saved_searches: Vec<SavedSearch>) {
+    self.apply(Operation::SavedSearches(saved_searches));
+  }
+
+  //where the export chooser starts: the last export folder, then downloads, then the data dir
+  //(minimal linux setups often have no downloads folder)
+  pub fn export_dir(&self) -> PathBuf {
+    let remembered: Option<PathBuf> = self.stored.as_ref().and_then(|stored| stored.settings.export_dir.clone());
+    if let Some(export_dir) = remembered.filter(|export_dir| export_dir.is_dir()) {
+      return export_dir;
+    }
+    directories_next::UserDirs::new().and_then(|user_dirs| user_dirs.download_dir().map(PathBuf::from)).unwrap_or_else(Storage::data_dir)
+  }
+
+  pub fn export_file_name(format: ExportFormat) -> String {
+    format!("reservoir_info_{}.{}", get_timestamp(), export::extension(format))
+  }
+
+  //would be nice to change to &Stored or some kind of pointer
+  //returns the path written, to show the user
+  pub async fn export(stored: Stored, format: ExportFormat, save_path: PathBuf) -> Result<PathBuf, StorageError> {
+    let mut save_file: File = File::create(&save_path).await.map_err(|error| StorageError::ExportError(error.to_string()))?;
+    save_file.write_all(export::render(&stored, format).as_bytes()).await.map_err(|error| StorageError::ExportError(error.to_string()))?;
+    save_file.sync_all().await.map_err(|error| StorageError::ExportError(error.to_string()))?;
+    Ok(save_path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  //a library as the first version saved it, links without https:// and tags as they were typed
+  fn first_version_library() -> Value {
+    json!({
+      "bookmarks": {
+        "a": { "title": "Rust", "link": "www.rust-lang.org", "tags": [" Lang / Rust/", "", "rust", "lang/rust"], "uuid": "a", "timestamp": 1 },
+        "b": { "title": "Notes", "link": "/home/me/notes.txt", "tags": [], "uuid": "b", "timestamp": 2 },
+        "c": { "title": "Mail", "link": "mailto:me@example.com", "tags": ["mail"], "uuid": "c", "timestamp": 3 },
+      },
+    })
+  }
+
+  #[test]
+  fn migrates_the_first_version_to_the_current_one() {
+    let mut value: Value = first_version_library();
+    migrate(&mut value, 0);
+    let stored: Stored = serde_json::from_value(value).unwrap();
+    assert_eq!(stored.schema_version, SCHEMA_VERSION);
+    assert_eq!(stored.bookmarks["a"].link, "https://www.rust-lang.org");
+    assert_eq!(stored.bookmarks["b"].link, "file:///home/me/notes.txt");
+    assert_eq!(stored.bookmarks["c"].link, "mailto:me@example.com");
+    assert_eq!(stored.bookmarks["a"].tags, vec!["Lang/Rust", "rust"]);
+    assert!(stored.trash.is_empty());
+    assert_eq!(stored.settings, Settings::default());
+    assert!(stored.saved_searches.is_empty());
+  }
+
+  #[test]
+  fn migrating_from_the_current_version_changes_nothing() {
+    let mut value: Value = serde_json::to_value(Stored::empty()).unwrap();
+    let before: Value = value.clone();
+    migrate(&mut value, SCHEMA_VERSION);
+    assert_eq!(value, before);
+  }
+
+  #[test]
+  fn each_step_sets_its_version() {
+    let mut value: Value = first_version_library();
+    migrate(&mut value, 0);
+    assert_eq!(value["schema_version"], json!(SCHEMA_VERSION));
+    let mut value: Value = json!({ "bookmarks": {}, "trash": {}, "schema_version": 3 });
+    migrate(&mut value, 3);
+    assert_eq!(value["saved_searches"], json!([]));
+    assert_eq!(value["schema_version"], json!(SCHEMA_VERSION));
+  }
+
+  #[test]
+  fn tag_steps_do_what_they_did_when_they_were_added() {
+    let tags = |value: &Value, map_key: &str| value[map_key]["a"]["tags"].clone();
+    let mut value: Value = json!({
+      "bookmarks": { "a": { "tags": [" async ", "Async", "lang / rust", ""] } },
+      "trash": { "a": { "tags": ["lang/rust", " lang / rust "] } },
+    });
+    //v5 only trims, the / only means something from v6 on
+    MIGRATIONS[4](&mut value);
+    assert_eq!(tags(&value, "bookmarks"), json!(["async", "lang / rust"]));
+    assert_eq!(tags(&value, "trash"), json!(["lang/rust", "lang / rust"]));
+    MIGRATIONS[5](&mut value);
+    assert_eq!(tags(&value, "bookmarks"), json!(["async", "lang/rust"]));
+    assert_eq!(tags(&value, "trash"), json!(["lang/rust"]));
   }
 }
@@ -5,6 +5,8 @@ use iced::{ Background, Color, Theme, theme };
 use iced::widget::{ button, container };
 //use iced::overlay::Element;
 
+use crate::tags;
+
 #[derive(Default)]
 pub struct BookmarkContainer;
 
@@ -26,9 +28,30 @@ pub struct TagButton {
 }
 
 impl TagButton {
+  //tags under another one (lang/rust) are a shade of their parent's color, so a family of tags looks alike
   fn text_to_color(&self) -> [u8; 3] {
+    let mut tags = tags::with_ancestors(&self.text).into_iter();
+    let mut rgb: [u8; 3] = TagButton::hash_color(tags.next().unwrap_or_default());
+    for tag in tags {
+      let mut hasher = DefaultHasher::new();
+      tag.hash(&mut hasher);
+      let byte: u8 = hasher.finish().to_le_bytes()[0];
+      //lighter or darker by 15 to 35 percent, picked by the child's own hash
+      let amount: f32 = 0.15 + (byte % 21) as f32 / 100.0;
+      rgb = rgb.map(|channel| {
+        if byte & 1 == 0 {
+          channel + ((255 - channel) as f32 * amount) as u8
+        } else {
+          channel - (channel as f32 * amount) as u8
+        }
+      });
+    }
+    rgb
+  }
+
+  fn hash_color(text: &str) -> [u8; 3] {
     let mut hasher = DefaultHasher::new();
-    self.text.hash(&mut hasher);
+    text.hash(&mut hasher);
     let bytes: [u8; 8] = hasher.finish().to_le_bytes();
     const THRESHOLD: u8 = 90;
     let mut return_rgb: [u8; 3];
@@ -69,3 +92,5 @@ impl button::StyleSheet for TagButton {
 }
 
 pub const BOOKMARK_TIMESTAMP_STYLE: theme::Text = theme::Text::Color(Color::from_rgb(00.5, 0.5, 0.5));
+//the part of a title or link that matched the search
+pub const SEARCH_HIGHLIGHT_STYLE: theme::Text = theme::Text::Color(Color::from_rgb(1.0, 0.8, 0.3));
@@ -0,0 +1,223 @@
+use std::collections::{ BTreeSet, HashMap, HashSet };
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, text, text_input, scrollable, row, column, Column };
+
+use crate::storage::{ Bookmark, Storage };
+use crate::styles;
+use crate::tags::{ self, normalize_tags, parse_tag };
+
+#[derive(Clone, Debug)]
+pub enum TagManagerMessage {
+  Select(String),
+  TargetInput(String),
+  Preview(TagAction),
+  CancelPreview,
+  Confirm,
+  Close,
+}
+
+impl TagManagerMessage {
+  pub fn is_save_after(message: &TagManagerMessage) -> bool {
+    matches!(message, TagManagerMessage::Confirm)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TagAction {
+  //the selected tags all become this one. renaming is merging a single tag
+  Merge(String),
+  Delete,
+}
+
+pub struct TagManager {
+  selected: BTreeSet<String>,
+  target: String,
+  //the action waiting to be confirmed, while its preview is shown
+  action: Option<TagAction>,
+  //what the page shows, worked out when the library, the selection or the action change instead of on every redraw
+  counts: Vec<(String, usize)>,
+  affected: Vec<(Bookmark, Vec<String>)>,
+  error: Option<String>,
+}
+
+impl TagManager {
+  pub fn new() -> TagManager {
+    TagManager {
+      selected: BTreeSet::new(),
+      target: String::new(),
+      action: None,
+      counts: Vec::new(),
+      affected: Vec::new(),
+      error: None,
+    }
+  }
+
+  //every tag in the library and every tag above one, with how many bookmarks are under it. children after their parent
+  fn tag_counts(storage: &Storage) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for bookmark in storage.stored.as_ref().unwrap().bookmarks.values() {
+      let under: HashSet<&str> = bookmark.tags.iter().flat_map(|tag| tags::with_ancestors(tag)).collect();
+      for tag in under {
+        *counts.entry(tag).or_default() += 1;
+      }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().map(|(tag, count)| (tag.to_string(), count)).collect();
+    counts.sort_unstable_by(|(tag1, _), (tag2, _)| tag1.split('/').cmp(tag2.split('/')));
+    counts
+  }
+
+  //the outermost selected tag this one is under, so a selected parent moves its whole family along
+  fn selected_above<'a>(tag: &str, selected: &'a BTreeSet<String>) -> Option<&'a String> {
+    selected.iter().filter(|selected_tag| tags::is_under(tag, selected_tag)).min_by_key(|selected_tag| selected_tag.len())
+  }
+
+  //a bookmark's tags after the action. the new tag goes where the first replaced one was,
+  //and tags under a renamed one move with it (lang/rust becomes code/rust when lang becomes code)
+  fn retag(tags: &[String], selected: &BTreeSet<String>, action: &TagAction, lowercase: bool) -> Vec<String> {
+    let new_tags = tags.iter().filter_map(|tag| match (TagManager::selected_above(tag, selected), action) {
+      (None, _) => Some(tag.clone()),
+      (Some(selected_tag), TagAction::Merge(target)) => Some(format!("{}{}", target, &tag[selected_tag.len()..])),
+      (Some(_), TagAction::Delete) => None,
+    });
+    normalize_tags(new_tags, lowercase)
+  }
+
+  //the bookmarks the action would change, before and after, oldest first
+  fn affected(&self, storage: &Storage, action: &TagAction) -> Vec<(Bookmark, Vec<String>)> {
+    let lowercase: bool = storage.stored.as_ref().unwrap().settings.lowercase_tags;
+    let mut affected: Vec<(Bookmark, Vec<String>)> = storage.stored.as_ref().unwrap().bookmarks.values().filter(|bookmark| {
+      bookmark.tags.iter().any(|tag| TagManager::selected_above(tag, &self.selected).is_some())
+    }).map(|bookmark| (bookmark.clone(), TagManager::retag(&bookmark.tags, &self.selected, action, lowercase))).collect();
+    affected.sort_unstable_by(|(bookmark1, _), (bookmark2, _)| bookmark1.timestamp.cmp(&bookmark2.timestamp).then_with(|| bookmark1.uuid.cmp(&bookmark2.uuid)));
+    affected
+  }
+
+  //after the bookmarks changed
+  pub fn refresh(&mut self, storage: &Storage) {
+    self.counts = TagManager::tag_counts(storage);
+    self.affected = match &self.action {
+      Some(action) => self.affected(storage, action),
+      None => Vec::new(),
+    };
+  }
+
+  pub fn update(&mut self, message: TagManagerMessage, storage: &mut Storage) {
+    match message {
+      TagManagerMessage::Select(tag) => {
+        if !self.selected.remove(&tag) {
+          self.selected.insert(tag);
+        }
+        self.error = None;
+      },
+      TagManagerMessage::TargetInput(target) => {
+        self.target = target;
+        self.error = None;
+      },
+      TagManagerMessage::Preview(action) => {
+        if self.selected.is_empty() {
+          self.error = Some("Select the tags to change first".to_string());
+          return;
+        }
+        //the new name is checked like any typed tag
+        self.action = match action {
+          TagAction::Merge(target) => match parse_tag(&target, &storage.stored.as_ref().unwrap().settings) {
+            Ok(target) => Some(TagAction::Merge(target)),
+            Err(error) => {
+              self.error = Some(error);
+              None
+            },
+          },
+          TagAction::Delete => Some(TagAction::Delete),
+        };
+        self.refresh(storage);
+      },
+      TagManagerMessage::CancelPreview => {
+        self.action = None;
+        self.affected = Vec::new();
+      },
+      TagManagerMessage::Confirm => {
+        let Some(action) = self.action.take() else {
+          return;
+        };
+        //all of them are saved together, so one undo puts every bookmark back
+        for (bookmark, tags) in std::mem::take(&mut self.affected) {
+          storage.add_bookmark(Bookmark {
+            tags,
+            ..bookmark
+          });
+        }
+        self.selected = match action {
+          TagAction::Merge(target) => BTreeSet::from([target]),
+          TagAction::Delete => BTreeSet::new(),
+        };
+        self.target = String::new();
+      },
+      TagManagerMessage::Close => {
+        self.action = None;
+        self.affected = Vec::new();
+        self.error = None;
+      },
+    }
+  }
+
+  fn preview_view<'a>(&'a self, action: &TagAction) -> Element<'a, TagManagerMessage> {
+    let selected: String = self.selected.iter().cloned().collect::<Vec<String>>().join(", ");
+    let summary: String = match action {
+      TagAction::Merge(target) => format!("{} becomes {} on {} bookmarks, tags under it move along", selected, target, self.affected.len()),
+      TagAction::Delete => format!("{} and the tags under it are removed from {} bookmarks", selected, self.affected.len()),
+    };
+    let mut bookmark_elements: Vec<Element<TagManagerMessage>> = vec![text(summary).into()];
+    for (bookmark, tags) in &self.affected {
+      bookmark_elements.push(container(column![
+        text(&bookmark.title),
+        text(format!("{}  ->  {}", bookmark.tags.join(", "), if tags.is_empty() { "no tags".to_string() } else { tags.join(", ") })).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+      ]).style(theme::Container::Custom(Box::new(styles::BookmarkContainer))).padding(10).width(Length::Fill).into());
+    }
+    column![
+      row![
+        button("Back").on_press(TagManagerMessage::CancelPreview),
+        container(text("Preview").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+        button("Apply").on_press(TagManagerMessage::Confirm).style(theme::Button::Positive),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      scrollable(container(Column::with_children(bookmark_elements).spacing(10)).padding([0, 20, 10, 20])),
+    ].into()
+  }
+
+  pub fn view(&self) -> Element<'_, TagManagerMessage> {
+    if let Some(action) = &self.action {
+      return self.preview_view(action);
+    }
+    let mut tag_elements: Vec<Element<TagManagerMessage>> = Vec::new();
+    for (tag, count) in &self.counts {
+      let is_selected: bool = self.selected.contains(tag);
+      tag_elements.push(
+        container(
+          button(text(format!("{} ({})", tag, count))).width(Length::Fill).style(if is_selected { theme::Button::Primary } else { theme::Button::Secondary }).on_press(TagManagerMessage::Select(tag.to_string()))
+        ).padding([0, 0, 0, 20 * tags::depth(tag) as u16]).into()
+      );
+    }
+    if tag_elements.is_empty() {
+      tag_elements.push(text("No tags yet").into());
+    }
+    let rename_label: &str = if self.selected.len() > 1 { "Merge Into" } else { "Rename To" };
+    let mut action_row = row![
+      text(format!("{} selected", self.selected.len())),
+      text_input("New tag", &self.target).on_input(TagManagerMessage::TargetInput).on_submit(TagManagerMessage::Preview(TagAction::Merge(self.target.clone()))).width(Length::Fixed(200.0)),
+      button(rename_label).on_press(TagManagerMessage::Preview(TagAction::Merge(self.target.clone()))),
+      button("Delete").on_press(TagManagerMessage::Preview(TagAction::Delete)).style(theme::Button::Destructive),
+    ].spacing(5).align_items(Alignment::Center);
+    if let Some(error) = &self.error {
+      action_row = action_row.push(text(error));
+    }
+    column![
+      row![
+        button("Back").on_press(TagManagerMessage::Close),
+        container(text("Tags").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+      ].align_items(Alignment::Center).padding([10, 20]),
+      container(action_row).padding([0, 20, 10, 20]),
+      scrollable(container(Column::with_children(tag_elements).spacing(5)).padding([0, 20, 10, 20])),
+    ].into()
+  }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+
+use crate::storage::Settings;
+
+//every way tags get into the library goes through here, so " async", "" and a second "Rust" next to "rust" don't pile up.
+//a / in a tag makes it a child of the part before it: lang/rust/async is under lang/rust, which is under lang
+
+//trimmed, without empty ones, and without repeats (ignoring case, the first spelling is kept)
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>, lowercase: bool) -> Vec<String> {
+  let mut seen: HashSet<String> = HashSet::new();
+  let mut normalized: Vec<String> = Vec::new();
+  for tag in tags {
+    //"lang / rust/" is lang/rust
+    let tag: String = tag.split('/').map(str::trim).filter(|part| !part.is_empty()).collect::<Vec<&str>>().join("/");
+    let tag: String = if lowercase { tag.to_lowercase() } else { tag };
+    if !tag.is_empty() && seen.insert(tag.to_lowercase()) {
+      normalized.push(tag);
+    }
+  }
+  normalized
+}
+
+//the tag this one is under, if any
+pub fn parent(tag: &str) -> Option<&str> {
+  tag.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+//the last part of the tag, what it is called under its parent
+pub fn leaf(tag: &str) -> &str {
+  tag.rsplit_once('/').map(|(_, leaf)| leaf).unwrap_or(tag)
+}
+
+pub fn depth(tag: &str) -> usize {
+  tag.matches('/').count()
+}
+
+//whether the tag is this one or somewhere under it
+pub fn is_under(tag: &str, ancestor: &str) -> bool {
+  tag.strip_prefix(ancestor).map(|rest| rest.is_empty() || rest.starts_with('/')).unwrap_or(false)
+}
+
+//the tag and every tag above it, top first: lang, lang/rust, lang/rust/async
+pub fn with_ancestors(tag: &str) -> Vec<&str> {
+  let mut tags: Vec<&str> = tag.match_indices('/').map(|(index, _)| &tag[..index]).collect();
+  tags.push(tag);
+  tags
+}
+
+//tags may have letters, numbers, spaces and the characters allowed in the settings
+fn check_characters(tag: &str, settings: &Settings) -> Result<(), String> {
+  match tag.chars().find(|char| !(char.is_alphanumeric() || *char == ' ' || settings.tag_characters.contains(*char))) {
+    Some(char) => Err(format!("Tag \"{}\" can't have {} in it, tags can use letters, numbers, spaces and {}", tag.trim(), char, settings.tag_characters)),
+    None => Ok(()),
+  }
+}
+
+//tags that weren't typed in, like imported ones: normalized, without the ones that have characters the settings don't allow.
+//also how many were dropped
+pub fn allowed_tags(tags: impl IntoIterator<Item = String>, settings: &Settings) -> (Vec<String>, usize) {
+  let (allowed, dropped): (Vec<String>, Vec<String>) = normalize_tags(tags, settings.lowercase_tags).into_iter().partition(|tag| check_characters(tag, settings).is_ok());
+  (allowed, dropped.len())
+}
+
+//tags typed as comma separated values
+pub fn parse_tags(input: &str, settings: &Settings) -> Result<Vec<String>, String> {
+  for tag in input.split(',') {
+    check_characters(tag, settings)?;
+  }
+  Ok(normalize_tags(input.split(',').map(|tag| tag.to_string()), settings.lowercase_tags))
+}
+
+//a single tag, like the new name of one
+pub fn parse_tag(input: &str, settings: &Settings) -> Result<String, String> {
+  let mut tags: Vec<String> = parse_tags(input, settings)?;
+  match tags.len() {
+    0 => Err("Type a tag".to_string()),
+    1 => Ok(tags.remove(0)),
+    _ => Err("That is more than one tag, tags can't have commas in them".to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn strings(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|tag| tag.to_string()).collect()
+  }
+
+  #[test]
+  fn normalizing_trims_and_drops_empty_tags() {
+    assert_eq!(normalize_tags(strings(&[" rust ", "", "  ", "web dev"]), false), strings(&["rust", "web dev"]));
+  }
+
+  #[test]
+  fn normalizing_keeps_the_first_spelling() {
+    assert_eq!(normalize_tags(strings(&["Rust", "rust", "RUST", "go"]), false), strings(&["Rust", "go"]));
+    assert_eq!(normalize_tags(strings(&["Rust", "rust", "Go"]), true), strings(&["rust", "go"]));
+  }
+
+  #[test]
+  fn normalizing_cleans_up_the_parts_of_a_path() {
+    assert_eq!(normalize_tags(strings(&["lang / rust/", "/lang//rust", "Lang/Rust"]), false), strings(&["lang/rust"]));
+    assert_eq!(normalize_tags(strings(&["/", " / "]), false), Vec::<String>::new());
+  }
+
+  #[test]
+  fn under_means_the_tag_or_a_child_of_it() {
+    assert!(is_under("lang", "lang"));
+    assert!(is_under("lang/rust", "lang"));
+    assert!(is_under("lang/rust/async", "lang/rust"));
+    assert!(!is_under("language", "lang"));
+    assert!(!is_under("lang-x", "lang"));
+    assert!(!is_under("lang", "lang/rust"));
+    assert!(!is_under("code/lang", "lang"));
+  }
+
+  #[test]
+  fn ancestors_come_first() {
+    assert_eq!(with_ancestors("lang/rust/async"), vec!["lang", "lang/rust", "lang/rust/async"]);
+    assert_eq!(with_ancestors("rust"), vec!["rust"]);
+  }
+
+  #[test]
+  fn parent_leaf_and_depth() {
+    assert_eq!(parent("lang/rust/async"), Some("lang/rust"));
+    assert_eq!(parent("lang"), None);
+    assert_eq!(leaf("lang/rust/async"), "async");
+    assert_eq!(leaf("lang"), "lang");
+    assert_eq!(depth("lang/rust/async"), 2);
+    assert_eq!(depth("lang"), 0);
+  }
+
+  #[test]
+  fn parsing_splits_at_commas() {
+    let settings: Settings = Settings::default();
+    assert_eq!(parse_tags("rust, web dev,, Rust,lang/rust", &settings), Ok(strings(&["rust", "web dev", "lang/rust"])));
+    assert_eq!(parse_tags("", &settings), Ok(Vec::new()));
+  }
+
+  #[test]
+  fn dropping_tags_with_other_characters() {
+    let settings: Settings = Settings {
+      lowercase_tags: true,
+      ..Settings::default()
+    };
+    assert_eq!(allowed_tags(strings(&["a,b", "Rust", "c++", "what?", "rust"]), &settings), (strings(&["rust", "c++"]), 2));
+    assert_eq!(allowed_tags(Vec::new(), &settings), (Vec::new(), 0));
+  }
+
+  #[test]
+  fn parsing_follows_the_settings() {
+    let settings: Settings = Settings {
+      lowercase_tags: true,
+      tag_characters: "-".to_string(),
+      ..Settings::default()
+    };
+    assert_eq!(parse_tags("Web-Dev", &settings), Ok(strings(&["web-dev"])));
+    assert_eq!(parse_tags("c++, rust", &settings), Err("Tag \"c++\" can't have + in it, tags can use letters, numbers, spaces and -".to_string()));
+    //without / allowed there is no hierarchy either
+    assert!(parse_tags("lang/rust", &settings).is_err());
+  }
+
+  #[test]
+  fn parsing_a_single_tag() {
+    let settings: Settings = Settings::default();
+    assert_eq!(parse_tag(" Rust ", &settings), Ok("Rust".to_string()));
+    assert_eq!(parse_tag(" , ", &settings), Err("Type a tag".to_string()));
+    assert_eq!(parse_tag("rust, go", &settings), Err("That is more than one tag, tags can't have commas in them".to_string()));
+  }
+}
@@ -0,0 +1,88 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use iced::Element;
+use iced::{ alignment, Alignment, Length, theme };
+use iced::widget::{ button, container, text, scrollable, row, column, Column };
+
+use crate::storage::{ Settings, Storage, TrashedBookmark, SECONDS_IN_DAY };
+use crate::utils::{ get_timestamp, timestamp_to_string };
+use crate::styles;
+
+#[derive(Clone, Debug)]
+pub enum TrashMessage {
+  Restore(String),
+  Delete(String),
+  EmptyTrash,
+  Close,
+}
+
+impl TrashMessage {
+  pub fn is_save_after(message: &TrashMessage) -> bool {
+    !matches!(message, TrashMessage::Close)
+  }
+}
+
+pub struct TrashList {}
+
+impl TrashList {
+  pub fn new() -> TrashList {
+    TrashList {}
+  }
+
+  pub fn update(&mut self, message: TrashMessage, storage: &mut Storage) {
+    match message {
+      TrashMessage::Restore(uuid) => {
+        storage.restore_trashed(uuid);
+      },
+      TrashMessage::Delete(uuid) => {
+        storage.purge_trashed(uuid);
+      },
+      TrashMessage::EmptyTrash => {
+        let uuids: Vec<String> = storage.stored.as_ref().unwrap().trash.keys().cloned().collect();
+        for uuid in uuids {
+          storage.purge_trashed(uuid);
+        }
+      },
+      _ => {},
+    }
+  }
+
+  pub fn view<'a>(&'a self, trash: &'a HashMap<String, TrashedBookmark>, settings: &Settings) -> Element<'a, TrashMessage> {
+    //most recently deleted first
+    let mut trashed: Vec<&TrashedBookmark> = trash.values().collect();
+    trashed.sort_unstable_by_key(|trashed| Reverse(trashed.deleted));
+    let now: u64 = get_timestamp();
+    let mut trash_elements: Vec<Element<TrashMessage>> = Vec::new();
+    for trashed in trashed {
+      let purge_at: u64 = trashed.deleted + settings.trash_days * SECONDS_IN_DAY;
+      let days_left: u64 = purge_at.saturating_sub(now).div_ceil(SECONDS_IN_DAY);
+      trash_elements.push(
+        row![
+          column![
+            text(&trashed.bookmark.title),
+            text(&trashed.bookmark.link).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+            text(format!("Deleted {}, gone in {} day{}", timestamp_to_string(trashed.deleted), days_left, if days_left == 1 { "" } else { "s" })).style(styles::BOOKMARK_TIMESTAMP_STYLE),
+          ].spacing(2).width(Length::Fill),
+          button("Restore").on_press(TrashMessage::Restore(trashed.bookmark.uuid.clone())),
+          button("Delete Forever").on_press(TrashMessage::Delete(trashed.bookmark.uuid.clone())).style(theme::Button::Destructive),
+        ].spacing(5).align_items(Alignment::Center).into()
+      );
+    }
+    if trash.is_empty() {
+      trash_elements.push(text(format!("Trash is empty. Deleted bookmarks stay here for {} days", settings.trash_days)).into());
+    }
+    let mut empty_button = button("Empty Trash").style(theme::Button::Destructive);
+    if !trash.is_empty() {
+      empty_button = empty_button.on_press(TrashMessage::EmptyTrash);
+    }
+    column![
+      row![
+        button("Back").on_press(TrashMessage::Close),
+        container(text("Trash").size(24)).width(Length::Fill).align_x(alignment::Horizontal::Center),
+        empty_button,
+      ].align_items(Alignment::Center).padding([10, 20]),
+      scrollable(container(Column::with_children(trash_elements).spacing(10)).padding([0, 20, 10, 20])),
+    ].into()
+  }
+}
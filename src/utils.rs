@@ -1,5 +1,8 @@
+use std::path::Path;
+use std::process::Command;
 use std::time::{ SystemTime, UNIX_EPOCH, Duration };
 
+use url::Url;
 use uuid::Uuid;
 use chrono::prelude::DateTime;
 use chrono::Local;
@@ -20,12 +23,71 @@ pub fn timestamp_to_string(timestamp: u64) -> String {
   local_datetime.format("%d/%m/%Y %H:%M").to_string()
 }
 
-pub fn normalize_link(link: String) -> String {
-  let mut link = link;
-  if link.starts_with("https://") {
-    link = link.replacen("https://", "", 1);
+//the scheme a link starts with, lowercased. "localhost:3000" is a host and port, not a scheme
+pub fn link_scheme(link: &str) -> Option<String> {
+  let (scheme, rest) = link.split_once(':')?;
+  let mut chars = scheme.chars();
+  //single letters are windows drives (C:\)
+  let valid: bool = scheme.len() > 1 && chars.next()?.is_ascii_alphabetic() && chars.all(|char| char.is_ascii_alphanumeric() || "+-.".contains(char));
+  if !valid || rest.starts_with(|char: char| char.is_ascii_digit()) {
+    return None;
   }
-  link
+  Some(scheme.to_lowercase())
+}
+
+//what gets stored for a typed in link. links without a scheme are assumed to be https, paths become file:// links
+pub fn full_link(link: &str) -> String {
+  let link: &str = link.trim();
+  if link.is_empty() || link_scheme(link).is_some() {
+    link.to_string()
+  } else if link.starts_with('/') {
+    format!("file://{}", link)
+  } else if link.len() > 2 && link.as_bytes()[1] == b':' && link.as_bytes()[0].is_ascii_alphabetic() {
+    format!("file:///{}", link.replace('\\', "/"))
+  } else {
+    format!("https://{}", link)
+  }
+}
+
+//shorter form of a link for showing in the list, https is what people expect so it is left out
+pub fn display_link(link: &str) -> &str {
+  link.strip_prefix("https://").unwrap_or(link)
+}
+
+//the form of a link used to tell whether two links are the same page. http and https, www., letter case in the
+//host, default ports, a trailing slash, an empty fragment and tracking parameters are all ignored.
+//tracking parameters ending in * match anything starting with the rest (utm_*).
+//not meant to be shown or opened, if it can't be parsed it is just lowercased
+pub fn canonical_link(link: &str, tracking_parameters: &[String]) -> String {
+  let link: &str = link.trim();
+  let Ok(mut url) = Url::parse(&full_link(link)) else {
+    return link.to_lowercase();
+  };
+  let is_tracking = |name: &str| tracking_parameters.iter().any(|parameter| {
+    match parameter.strip_suffix('*') {
+      Some(prefix) => name.starts_with(prefix),
+      None => name == parameter,
+    }
+  });
+  let kept: Vec<(String, String)> = url.query_pairs().filter(|(name, _)| !is_tracking(name)).map(|(name, value)| (name.to_string(), value.to_string())).collect();
+  if kept.is_empty() {
+    url.set_query(None);
+  } else {
+    url.query_pairs_mut().clear().extend_pairs(kept);
+  }
+  if url.fragment() == Some("") {
+    url.set_fragment(None);
+  }
+  //the url crate already lowercases the host, turns unicode hosts into punycode and drops default ports
+  let scheme: &str = url.scheme();
+  let host: String = url.host_str().map(|host| host.trim_start_matches("www.").to_string()).unwrap_or_default();
+  let port: String = url.port().map(|port| format!(":{}", port)).unwrap_or_default();
+  let path: &str = url.path().trim_end_matches('/');
+  let query: String = url.query().map(|query| format!("?{}", query)).unwrap_or_default();
+  let fragment: String = url.fragment().map(|fragment| format!("#{}", fragment)).unwrap_or_default();
+  let scheme: String = if scheme == "http" || scheme == "https" { String::new() } else { format!("{}:", scheme) };
+  let slashes: &str = if url.has_authority() && !scheme.is_empty() { "//" } else { "" };
+  format!("{}{}{}{}{}{}{}", scheme, slashes, host, port, path, query, fragment)
 }
 
 pub fn truncate_with_ellipses(input: &str, max_length: usize) -> String {
@@ -35,3 +97,71 @@ pub fn truncate_with_ellipses(input: &str, max_length: usize) -> String {
     input.to_string()
   }
 }
+
+//open a file or folder with whatever the os would normally use (file manager for folders)
+pub fn open_path(path: &Path) -> std::io::Result<()> {
+  #[cfg(target_os = "windows")]
+  let program: &str = "explorer";
+  #[cfg(target_os = "macos")]
+  let program: &str = "open";
+  #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+  let program: &str = "xdg-open";
+  Command::new(program).arg(path).spawn()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn canonical(link: &str) -> String {
+    canonical_link(link, &["utm_*".to_string(), "fbclid".to_string()])
+  }
+
+  #[test]
+  fn scheme_www_case_and_trailing_slash_dont_matter() {
+    assert_eq!(canonical("https://www.Example.com/page/"), "example.com/page");
+    assert_eq!(canonical("http://example.com/page"), "example.com/page");
+    assert_eq!(canonical("example.com/page"), "example.com/page");
+    //only the host is case insensitive
+    assert_ne!(canonical("https://example.com/Page"), canonical("https://example.com/page"));
+  }
+
+  #[test]
+  fn default_ports_are_dropped() {
+    assert_eq!(canonical("https://example.com:443/a"), canonical("https://example.com/a"));
+    assert_eq!(canonical("http://example.com:80/a"), canonical("https://example.com/a"));
+    assert_eq!(canonical("https://example.com:8080/a"), "example.com:8080/a");
+  }
+
+  #[test]
+  fn tracking_parameters_are_dropped() {
+    assert_eq!(canonical("https://example.com/a?utm_source=x&id=3&fbclid=y"), "example.com/a?id=3");
+    assert_eq!(canonical("https://example.com/a?utm_medium=email"), "example.com/a");
+    //utm_* only covers parameters starting with utm_
+    assert_eq!(canonical("https://example.com/a?utm=1"), "example.com/a?utm=1");
+  }
+
+  #[test]
+  fn only_empty_fragments_are_dropped() {
+    assert_eq!(canonical("https://example.com/a#"), "example.com/a");
+    assert_eq!(canonical("https://example.com/a#part"), "example.com/a#part");
+  }
+
+  #[test]
+  fn unicode_hosts_match_their_punycode() {
+    assert_eq!(canonical("https://bücher.de/"), canonical("https://xn--bcher-kva.de"));
+  }
+
+  #[test]
+  fn other_schemes_are_kept() {
+    assert_eq!(canonical("ftp://example.com/file"), "ftp://example.com/file");
+    assert_ne!(canonical("ftp://example.com/file"), canonical("https://example.com/file"));
+    assert_eq!(canonical("mailto:me@example.com"), "mailto:me@example.com");
+  }
+
+  #[test]
+  fn unparseable_links_are_lowercased() {
+    assert_eq!(canonical(" http://[Bad "), "http://[bad");
+  }
+}